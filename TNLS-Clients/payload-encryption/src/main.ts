@@ -41,6 +41,17 @@ document.querySelector<HTMLDivElement>('#app')!.innerHTML = `
       <br>
       <label for="input5">$USD value of salary/income stream:  </label>
       <input type="text" placeholder="$" id="input5" name="input5" />
+      <br>
+      <label for="payload_version">Payload encryption:  </label>
+      <select id="payload_version" name="payload_version">
+        <option value="1">1: ChaCha20-Poly1305</option>
+        <option value="2">2: ChaCha20-Poly1305, HKDF-SHA256</option>
+        <option value="3" selected>3: XChaCha20-Poly1305, HKDF-SHA256</option>
+        <option value="4">4: AES-256-GCM-SIV, HKDF-SHA256</option>
+        <option value="5">5: ChaCha20-Poly1305, HKDF-SHA256, associated data</option>
+        <option value="6">6: XChaCha20-Poly1305, HKDF-SHA256, associated data</option>
+        <option value="7">7: AES-256-GCM-SIV, HKDF-SHA256, associated data</option>
+      </select>
         <br>
       <button id="submit">Submit</button>
 
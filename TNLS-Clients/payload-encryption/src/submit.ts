@@ -1,6 +1,6 @@
-import { encrypt_payload } from "./wasm";
+import { encrypt_payload, payload_associated_data } from "./wasm";
 import { ethers } from "ethers";
-import { arrayify, hexlify, SigningKey, keccak256, recoverPublicKey, computeAddress } from "ethers/lib/utils";
+import { arrayify, hashMessage, hexlify, SigningKey, keccak256, recoverPublicKey, computeAddress, getAddress } from "ethers/lib/utils";
 import { Buffer } from "buffer/";
 import secureRandom from "secure-random";
 
@@ -53,20 +53,29 @@ export async function setupSubmit(element: HTMLButtonElement) {
         })
         
         const plaintext = Buffer.from(JSON.stringify(thePayload));
-        const nonce = secureRandom(12, { type: "Uint8Array" });
         const handle = "request_score"
 
+        // XChaCha20-Poly1305 (versions 3 and 6) takes 24 byte nonces, the other versions 12 byte nonces
+        const payloadVersion = Number(document.querySelector<HTMLSelectElement>('#payload_version')?.value ?? 1)
+        const nonce = secureRandom(payloadVersion == 3 || payloadVersion == 6 ? 24 : 12, { type: "Uint8Array" });
+
+        // versions 5-7 bind the task metadata to the ciphertext; the gateways see the checksummed user address
+        const associatedData = payloadVersion >= 5
+            ? payload_associated_data(handle, routing_code_hash, "ethereum", getAddress(myAddress))
+            : new Uint8Array();
+
         const ciphertext = Buffer.from(
         encrypt_payload(
             gatewayPublicKeyBytes,
             userPrivateKeyBytes,
             plaintext,
-            nonce
+            nonce,
+            payloadVersion,
+            associatedData
         ));
     
-        // const ciphertextHash = keccak256(ciphertext)
-        // const payloadHash = '0x' + sha3.keccak256("\x19Ethereum Signed Message:\n" + 32 + ciphertextHash.substring(2))
-        const payloadHash = keccak256(ciphertext)
+        // Ethereum signed message hash of the Keccak256 hash of the ciphertext, as the gateways recompute it
+        const payloadHash = hashMessage(arrayify(keccak256(ciphertext)))
         console.log(`Payload Hash: ${payloadHash}`)
 
         document.querySelector<HTMLDivElement>('#preview')!.innerHTML = `
@@ -149,12 +158,13 @@ export async function setupSubmit(element: HTMLButtonElement) {
         const _payloadHash = payloadHash
         const _info = {
             user_key: hexlify(user_key),
-            user_pubkey: user_pubkey,  // need the updated ABI before including this
+            user_pubkey: user_pubkey,
             routing_code_hash: routing_code_hash,
             handle: handle,
             nonce: hexlify(nonce),
             payload: hexlify(ciphertext),
-            payload_signature: payloadSignature
+            payload_signature: payloadSignature,
+            payload_version: payloadVersion
         }
 
         console.log(`_userAddress: ${_userAddress}
@@ -165,7 +175,7 @@ export async function setupSubmit(element: HTMLButtonElement) {
                 
         // create the abi interface and encode the function data
         const publicClientAddress = '0xCfa680267C8a594789dB69e11b4974f1D1328669'
-        const abi = [{"inputs":[{"internalType":"address","name":"_gatewayAddress","type":"address"}],"stateMutability":"nonpayable","type":"constructor"},{"anonymous":false,"inputs":[{"indexed":true,"internalType":"uint256","name":"taskId","type":"uint256"},{"indexed":false,"internalType":"bytes","name":"result","type":"bytes"}],"name":"ComputedResult","type":"event"},{"inputs":[],"name":"GatewayAddress","outputs":[{"internalType":"address","name":"","type":"address"}],"stateMutability":"view","type":"function"},{"inputs":[{"internalType":"uint256","name":"_taskId","type":"uint256"},{"internalType":"bytes","name":"_result","type":"bytes"}],"name":"callback","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"internalType":"address","name":"_userAddress","type":"address"},{"internalType":"string","name":"_sourceNetwork","type":"string"},{"internalType":"string","name":"_routingInfo","type":"string"},{"internalType":"bytes32","name":"_payloadHash","type":"bytes32"},{"components":[{"internalType":"bytes","name":"user_key","type":"bytes"},{"internalType":"string","name":"routing_code_hash","type":"string"},{"internalType":"string","name":"handle","type":"string"},{"internalType":"bytes12","name":"nonce","type":"bytes12"},{"internalType":"bytes","name":"payload","type":"bytes"},{"internalType":"bytes","name":"payload_signature","type":"bytes"}],"internalType":"struct Util.ExecutionInfo","name":"_info","type":"tuple"}],"name":"send","outputs":[],"stateMutability":"nonpayable","type":"function"}]
+        const abi = [{"inputs":[{"internalType":"address","name":"_gatewayAddress","type":"address"}],"stateMutability":"nonpayable","type":"constructor"},{"anonymous":false,"inputs":[{"indexed":true,"internalType":"uint256","name":"taskId","type":"uint256"},{"indexed":false,"internalType":"bytes","name":"result","type":"bytes"}],"name":"ComputedResult","type":"event"},{"inputs":[],"name":"GatewayAddress","outputs":[{"internalType":"address","name":"","type":"address"}],"stateMutability":"view","type":"function"},{"inputs":[{"internalType":"uint256","name":"_taskId","type":"uint256"},{"internalType":"bytes","name":"_result","type":"bytes"}],"name":"callback","outputs":[],"stateMutability":"nonpayable","type":"function"},{"inputs":[{"internalType":"address","name":"_userAddress","type":"address"},{"internalType":"string","name":"_sourceNetwork","type":"string"},{"internalType":"string","name":"_routingInfo","type":"string"},{"internalType":"bytes32","name":"_payloadHash","type":"bytes32"},{"components":[{"internalType":"bytes","name":"user_key","type":"bytes"},{"internalType":"bytes","name":"user_pubkey","type":"bytes"},{"internalType":"string","name":"routing_code_hash","type":"string"},{"internalType":"string","name":"handle","type":"string"},{"internalType":"bytes","name":"nonce","type":"bytes"},{"internalType":"bytes","name":"payload","type":"bytes"},{"internalType":"bytes","name":"payload_signature","type":"bytes"},{"internalType":"uint8","name":"payload_version","type":"uint8"}],"internalType":"struct Util.ExecutionInfo","name":"_info","type":"tuple"}],"name":"send","outputs":[],"stateMutability":"nonpayable","type":"function"}]
         const iface= new ethers.utils.Interface( abi )
         const FormatTypes = ethers.utils.FormatTypes;
         console.log(iface.format(FormatTypes.full))
@@ -211,7 +221,7 @@ export async function setupSubmit(element: HTMLButtonElement) {
         <h2>Other Info</h2>
         <p>
 
-        <b>Encryption method:</b> ChaCha20Poly1305 <br>
+        <b>Payload version:</b> ${payloadVersion} <br>
         <b>Public key used during encryption:</b> ${userPublicKey} <br>
         <b>Nonce used during encryption:</b> ${nonce} <br>
 
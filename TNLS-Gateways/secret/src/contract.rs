@@ -1,6 +1,7 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult,
+    attr_plaintext, coins, entry_point, to_binary, Addr, Attribute, BankMsg, Binary, CanonicalAddr,
+    CosmosMsg, Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdError, StdResult,
+    Storage, SubMsg, SubMsgResult, Uint128,
 };
 use secret_toolkit::{
     crypto::secp256k1::{PrivateKey, PublicKey},
@@ -10,10 +11,23 @@ use secret_toolkit::{
 
 use crate::{
     msg::{
-        ExecuteMsg, InputResponse, InstantiateMsg, PostExecutionMsg, PreExecutionMsg,
-        PublicKeyResponse, QueryMsg, ResponseStatus::Success, SecretMsg,
+        AdminAction, AdminResponse, BatchInputResult, ConfigResponse, CouncilResponse, ExecuteMsg,
+        FailureCode, FailureResult, InputBatchResponse, InputResponse, InstantiateMsg,
+        KeyGenStatusResponse, MigrateMsg, PauseScope, PauseStatusResponse, PayloadVersion,
+        PendingTask, PendingTasksResponse, PostExecutionMsg, PreExecutionMsg, ProposalResponse,
+        PublicKeyResponse, QueryMsg, RelayerStatus, RelayersResponse,
+        ResponseStatus::{Failure, Success},
+        RouteResponse, RoutesResponse, SecretMsg, SigningScheme, SigningSchemeResponse, TaskStatus,
+        TaskStatusResponse,
     },
-    state::{KeyPair, State, TaskInfo, CONFIG, CREATOR, MY_ADDRESS, PRNG_SEED, TASK_MAP},
+    state::{
+        v0, ClosedTask, Council, KeyEpoch, KeyGenStatus, KeyPair, Proposal, RelayerInfo, ReplyTask,
+        RouteInfo, State, TaskCounters, TaskInfo, TaskKey, TaskOutcome, CLOSED_TASKS, CONFIG,
+        COUNCIL, CREATOR, CURRENT_VERSION, KEY_HISTORY, MY_ADDRESS, PAUSED_NETWORKS, PAUSED_ROUTES,
+        PRNG_SEED, PROPOSALS, RELAYERS, REPLY_TASKS, ROUTES, ROUTE_FEES, ROUTE_TIMEOUTS,
+        SEEN_NONCES, SIGNING_SCHEMES, TASK_COUNTERS, TASK_MAP, VERSION,
+    },
+    types::Payload,
     PrivContractHandleMsg,
 };
 
@@ -24,6 +38,53 @@ use sha3::{Digest, Keccak256};
 /// response size
 pub const BLOCK_SIZE: usize = 256;
 
+/// purpose attached to every randomness request sent to scrt-rng
+pub const RNG_PURPOSE: &str = "secret gateway entropy";
+
+/// number of blocks (~24 hours) a rotated out encryption key keeps decrypting payloads
+pub const DEFAULT_KEY_GRACE_PERIOD: u64 = 14_400;
+
+/// page size used by paginated queries when none is given
+pub const DEFAULT_PAGE_SIZE: u32 = 30;
+
+/// number of blocks (~2 hours) a task can stay pending before it can be expired
+pub const DEFAULT_TASK_TIMEOUT: u64 = 1_200;
+
+/// number of tasks removed by a single `ExpireTasks` when no limit is given
+pub const DEFAULT_EXPIRY_LIMIT: u32 = 10;
+
+/// number of nonces removed by a single `PruneNonces` when no limit is given
+pub const DEFAULT_PRUNE_LIMIT: u32 = 50;
+
+/// denomination of task fees
+pub const FEE_DENOM: &str = "uscrt";
+
+/// prefix of Ethereum signed messages, a requirement of Ethereum's `ecrecover` function
+pub const EVM_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n32";
+
+/// EIP-712 domain of the public gateways
+pub const EIP712_DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+pub const EIP712_NAME: &str = "TNLS Gateway";
+pub const EIP712_VERSION: &str = "1";
+
+/// EIP-712 types of signed results and packets
+pub const EIP712_RESULT_TYPE: &str = "Result(bytes result,bytes payload,bytes32 inputHash)";
+pub const EIP712_PACKET_TYPE: &str = concat!(
+    "Packet(string sourceNetwork,string destinationNetwork,uint256 taskId,bytes payload,",
+    "bytes payloadHash,bytes result,bytes32 resultHash,bytes resultSignature)"
+);
+
+/// domain separator of the gateway's ed25519 key, derived from its secp256k1 signing key
+pub const ED25519_KEY_PURPOSE: &str = "secret gateway ed25519 key";
+
+/// error reported to the public network for tasks that timed out
+pub const TIMEOUT_ERROR: &str = "task timed out";
+
+/// error reported to the public network for tasks whose private contract call failed
+/// NOTE: the private contract's own error is not forwarded, it may reveal private inputs
+pub const EXECUTION_ERROR: &str = "private contract call failed";
+
 #[cfg(feature = "contract")]
 ////////////////////////////////////// Init ///////////////////////////////////////
 /// Returns InitResult
@@ -57,29 +118,221 @@ pub fn instantiate(
         .transpose()?
         .unwrap_or(creator_raw);
 
+    // Save the scrt-rng contract that is trusted to provide entropy
+    let rng_addr_raw = deps.api.addr_canonicalize(msg.rng_addr.as_str())?;
+
     // Save both key pairs
     let state = State {
         admin: admin_raw,
-        keyed: false,
+        pending_admin: None,
+        rng_addr: rng_addr_raw,
+        rng_hash: msg.rng_hash.clone(),
+        key_gen_status: KeyGenStatus::Requested,
+        key_epoch: 0,
+        key_grace_period: msg.key_grace_period.unwrap_or(DEFAULT_KEY_GRACE_PERIOD),
+        permissionless: msg.permissionless.unwrap_or(false),
+        paused: false,
+        task_timeout: msg.task_timeout.unwrap_or(DEFAULT_TASK_TIMEOUT),
         tx_cnt: 0,
+        outbound_cnt: 0,
         encryption_keys: KeyPair::default(),
         signing_keys: KeyPair::default(),
     };
 
     CONFIG.save(deps.storage, &state)?;
+    VERSION.save(deps.storage, &CURRENT_VERSION)?;
+    TASK_COUNTERS.save(deps.storage, &TaskCounters::default())?;
 
     // create a message to request randomness from scrt-rng oracle
-    let rng_msg = SecretMsg::CreateRn {
-        cb_msg: Binary(vec![]),
-        entropy: msg.entropy,
-        max_blk_delay: None,
-        purpose: Some("secret gateway entropy".to_string()),
-        receiver_addr: Some(env.contract.address),
-        receiver_code_hash: env.contract.code_hash,
+    let rng_msg = request_randomness(env, msg.entropy, msg.rng_hash, msg.rng_addr)?;
+
+    Ok(Response::new()
+        .add_message(rng_msg)
+        .add_attribute_plaintext("key_gen_status", "requested"))
+}
+
+#[cfg(feature = "contract")]
+///////////////////////////////////// Migrate /////////////////////////////////////
+/// Returns MigrateResult
+///
+/// Upgrades the storage written by older versions of the contract to the current layout
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - MigrateMsg passed in with the migrate message
+#[entry_point]
+pub fn migrate(mut deps: DepsMut, env: Env, msg: MigrateMsg) -> StdResult<Response> {
+    // contracts deployed before the layout was versioned have no stored version
+    let from_version = VERSION.may_load(deps.storage)?.unwrap_or(0);
+    if from_version > CURRENT_VERSION {
+        return Err(StdError::generic_err(format!(
+            "cannot migrate from newer storage version {}",
+            from_version
+        )));
+    }
+
+    // upgrade one layout version at a time
+    for version in from_version..CURRENT_VERSION {
+        match version {
+            0 => migrate_from_v0(deps.branch(), &msg)?,
+            1 => migrate_from_v1(deps.branch(), &env, &msg)?,
+            2 => migrate_from_v2(deps.branch())?,
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "no migration from storage version {}",
+                    version
+                )))
+            }
+        }
+    }
+    VERSION.save(deps.storage, &CURRENT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute_plaintext("from_version", from_version.to_string())
+        .add_attribute_plaintext("to_version", CURRENT_VERSION.to_string()))
+}
+
+/// Returns StdResult<()>
+///
+/// upgrades the original, unversioned layout. The gateway keys become key epoch 0 and pending
+/// tasks are moved to the source network keyed task map, credited to the admin.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `msg` - MigrateMsg passed in with the migrate message
+fn migrate_from_v0(deps: DepsMut, msg: &MigrateMsg) -> StdResult<()> {
+    let legacy_state = v0::CONFIG.load(deps.storage)?;
+
+    // fields added by later storage versions are filled in by their own migrations
+
+    // the original layout did not store the scrt-rng contract
+    let (rng_addr, rng_hash) = match (&msg.rng_addr, &msg.rng_hash) {
+        (Some(rng_addr), Some(rng_hash)) => (rng_addr, rng_hash),
+        _ => {
+            return Err(StdError::generic_err(
+                "rng_addr and rng_hash are required to migrate from storage version 0",
+            ))
+        }
+    };
+    let rng_addr = deps.api.addr_validate(rng_addr.as_str())?;
+
+    // every pending task needs a route before anything is written
+    let legacy_tasks = v0::TASK_MAP
+        .iter(deps.storage)?
+        .collect::<StdResult<Vec<(u64, v0::TaskInfo)>>>()?;
+    let mut routed_tasks = Vec::with_capacity(legacy_tasks.len());
+    for (task_id, legacy_task) in legacy_tasks {
+        let route = msg
+            .task_routes
+            .iter()
+            .find(|route| route.task_id == task_id)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("missing route for pending task {}", task_id))
+            })?;
+        routed_tasks.push((task_id, legacy_task, route));
+    }
+
+    let state = State {
+        admin: legacy_state.admin,
+        pending_admin: None,
+        rng_addr: deps.api.addr_canonicalize(rng_addr.as_str())?,
+        rng_hash: rng_hash.clone(),
+        key_gen_status: if legacy_state.keyed {
+            KeyGenStatus::Keyed
+        } else {
+            KeyGenStatus::Requested
+        },
+        key_epoch: 0,
+        key_grace_period: msg.key_grace_period.unwrap_or(DEFAULT_KEY_GRACE_PERIOD),
+        permissionless: msg.permissionless.unwrap_or(true),
+        paused: false,
+        task_timeout: 0,
+        tx_cnt: legacy_state.tx_cnt,
+        outbound_cnt: 0,
+        encryption_keys: legacy_state.encryption_keys,
+        signing_keys: legacy_state.signing_keys,
+    };
+    CONFIG.save(deps.storage, &state)?;
+
+    // the original layout did not record which relayer submitted a task
+    let relayer = deps.api.addr_humanize(&state.admin)?;
+
+    // entries under the old namespace are left in place, they are never read again
+    for (task_id, legacy_task, route) in routed_tasks {
+        let task_info = TaskInfo {
+            payload: legacy_task.payload,
+            payload_hash: legacy_task.payload_hash,
+            input_hash: legacy_task.input_hash,
+            source_network: legacy_task.source_network,
+            user_address: legacy_task.user_address,
+            routing_info: deps.api.addr_validate(route.routing_info.as_str())?,
+            routing_code_hash: route.routing_code_hash.clone(),
+            key_epoch: 0,
+            relayer: relayer.clone(),
+            accepted_height: 0,
+            accepted_time: 0,
+            expires_at: 0,
+            fee: Uint128::zero(),
+            result_key: None,
+            payload_version: None,
+            reply_id: None,
+        };
+        let task_key: TaskKey = (task_info.source_network.clone(), task_id);
+        TASK_MAP.insert(deps.storage, &task_key, &task_info)?;
+    }
+
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// adds task timeouts. Tasks pending at the time of the migration are treated as accepted in the
+/// migration block.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - MigrateMsg passed in with the migrate message
+fn migrate_from_v1(deps: DepsMut, env: &Env, msg: &MigrateMsg) -> StdResult<()> {
+    let mut state = CONFIG.load(deps.storage)?;
+    state.task_timeout = msg.task_timeout.unwrap_or(DEFAULT_TASK_TIMEOUT);
+    CONFIG.save(deps.storage, &state)?;
+
+    let pending_tasks = TASK_MAP
+        .iter(deps.storage)?
+        .collect::<StdResult<Vec<(TaskKey, TaskInfo)>>>()?;
+    for (task_key, mut task_info) in pending_tasks {
+        task_info.accepted_height = env.block.height;
+        task_info.accepted_time = env.block.time.seconds();
+        task_info.expires_at =
+            env.block.height + task_timeout(deps.storage, &state, &task_info.routing_info);
+        TASK_MAP.insert(deps.storage, &task_key, &task_info)?;
     }
-    .to_cosmos_msg(msg.rng_hash, msg.rng_addr.into_string(), None)?;
 
-    Ok(Response::new().add_message(rng_msg))
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// adds gateway wide task counters. Counting starts at the migration, with tasks pending at the
+/// time of the migration counted as accepted.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+fn migrate_from_v2(deps: DepsMut) -> StdResult<()> {
+    let accepted = TASK_MAP.get_len(deps.storage)? as u64;
+    TASK_COUNTERS.save(
+        deps.storage,
+        &TaskCounters {
+            accepted,
+            ..TaskCounters::default()
+        },
+    )
 }
 
 #[cfg(feature = "contract")]
@@ -92,67 +345,242 @@ pub fn instantiate(
 /// * `env` - Env of contract's environment
 /// * `msg` - HandleMsg passed in with the execute message
 #[entry_point]
-pub fn execute(
-    deps: DepsMut,
-    env: Env,
-    _info: MessageInfo,
-    msg: ExecuteMsg,
-) -> StdResult<Response> {
+pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
     match msg {
-        ExecuteMsg::KeyGen { rng_hash, rng_addr } => {
-            pad_handle_result(try_fulfill_rn(deps, env, rng_hash, rng_addr), BLOCK_SIZE)
-        }
+        ExecuteMsg::KeyGen {} => pad_handle_result(try_fulfill_rn(deps, env, info), BLOCK_SIZE),
         ExecuteMsg::ReceiveFRn {
             cb_msg: _,
-            purpose: _,
+            purpose,
             rn,
-        } => pad_handle_result(create_gateway_keys(deps, env, rn), BLOCK_SIZE),
+        } => pad_handle_result(
+            create_gateway_keys(deps, env, info, purpose, rn),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::RotateKeys { entropy } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::RotateKeys { entropy }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::SetKeyGracePeriod { blocks } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::SetKeyGracePeriod { blocks }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::RevokeKeyEpoch { epoch } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::RevokeKeyEpoch { epoch }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::AddRelayers { relayers } => {
+            pad_handle_result(try_set_relayers(deps, info, relayers, true), BLOCK_SIZE)
+        }
+        ExecuteMsg::RemoveRelayers { relayers } => {
+            pad_handle_result(try_set_relayers(deps, info, relayers, false), BLOCK_SIZE)
+        }
+        ExecuteMsg::SetPermissionless { permissionless } => pad_handle_result(
+            try_set_permissionless(deps, info, permissionless),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::Pause { scope } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::Pause { scope }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::Unpause { scope } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::Unpause { scope }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::ProposeAdmin { new_admin } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::ProposeAdmin { new_admin }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::AcceptAdmin {} => pad_handle_result(try_accept_admin(deps, info), BLOCK_SIZE),
+        ExecuteMsg::SetRoute {
+            routing_info,
+            code_hash,
+            handles,
+        } => pad_handle_result(
+            try_admin_action(
+                deps,
+                env,
+                info,
+                AdminAction::SetRoute {
+                    routing_info,
+                    code_hash,
+                    handles,
+                },
+            ),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::RemoveRoute { routing_info } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::RemoveRoute { routing_info }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::SetSigningScheme { network, scheme } => pad_handle_result(
+            try_admin_action(
+                deps,
+                env,
+                info,
+                AdminAction::SetSigningScheme { network, scheme },
+            ),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::SetCouncil { members, threshold } => pad_handle_result(
+            try_admin_action(
+                deps,
+                env,
+                info,
+                AdminAction::SetCouncil { members, threshold },
+            ),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::Propose { action } => {
+            pad_handle_result(try_propose(deps, env, info, action), BLOCK_SIZE)
+        }
+        ExecuteMsg::Approve { proposal_id } => {
+            pad_handle_result(try_approve(deps, env, info, proposal_id), BLOCK_SIZE)
+        }
         ExecuteMsg::Input { inputs } => {
-            pad_handle_result(pre_execution(deps, env, inputs), BLOCK_SIZE)
+            pad_handle_result(pre_execution(deps, env, info, inputs), BLOCK_SIZE)
         }
-        ExecuteMsg::Output { outputs } => post_execution(deps, env, outputs),
+        ExecuteMsg::InputBatch { inputs } => {
+            pad_handle_result(pre_execution_batch(deps, env, info, inputs), BLOCK_SIZE)
+        }
+        ExecuteMsg::SetTaskTimeout { blocks } => pad_handle_result(
+            try_admin_action(deps, env, info, AdminAction::SetTaskTimeout { blocks }),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::SetRouteTimeout {
+            routing_info,
+            blocks,
+        } => pad_handle_result(
+            try_admin_action(
+                deps,
+                env,
+                info,
+                AdminAction::SetRouteTimeout {
+                    routing_info,
+                    blocks,
+                },
+            ),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::SetRouteFee {
+            routing_info,
+            min_fee,
+        } => pad_handle_result(
+            try_admin_action(
+                deps,
+                env,
+                info,
+                AdminAction::SetRouteFee {
+                    routing_info,
+                    min_fee,
+                },
+            ),
+            BLOCK_SIZE,
+        ),
+        ExecuteMsg::ClaimFees {} => pad_handle_result(try_claim_fees(deps, info), BLOCK_SIZE),
+        ExecuteMsg::ExpireTasks { limit } => expire_tasks(deps, env, limit),
+        ExecuteMsg::PruneNonces { limit } => prune_nonces(deps, env, limit),
+        ExecuteMsg::Output { outputs } => post_execution(deps, env, info, outputs),
+        ExecuteMsg::Outbound {
+            destination_network,
+            result,
+        } => outbound(deps, env, info, destination_network, result),
     }
 }
 
-fn try_fulfill_rn(
-    deps: DepsMut,
-    env: Env,
-    rng_hash: String,
-    rng_addr: Addr,
-) -> StdResult<Response> {
+#[cfg(feature = "contract")]
+////////////////////////////////////// Reply //////////////////////////////////////
+/// Returns ReplyResult
+///
+/// Handles the result of a private contract call sent by `Input`
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `msg` - Reply containing the ID and result of the submessage
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
+    match msg.result {
+        SubMsgResult::Err(_) => private_call_failed(deps, env, msg.id),
+        // private contract calls only reply on error
+        SubMsgResult::Ok(_) => Err(StdError::generic_err(format!(
+            "unexpected reply ID {}",
+            msg.id
+        ))),
+    }
+}
+
+fn try_fulfill_rn(deps: DepsMut, env: Env, info: MessageInfo) -> StdResult<Response> {
     // load config
-    let state = CONFIG.load(deps.storage)?;
+    let mut state = CONFIG.load(deps.storage)?;
+
+    // only the admin can trigger key generation
+    check_admin(deps.as_ref(), &info, &state)?;
 
     // check if the keys have already been created
-    if state.keyed {
+    if state.key_gen_status == KeyGenStatus::Keyed {
         return Err(StdError::generic_err(
             "keys have already been created".to_string(),
         ));
     }
 
+    let rng_addr = deps.api.addr_humanize(&state.rng_addr)?;
     let fulfill_rn_msg = SecretMsg::FulfillRn {
         creator_addr: env.contract.address,
-        purpose: Some("secret gateway entropy".to_string()),
+        purpose: Some(RNG_PURPOSE.to_string()),
         receiver_code_hash: env.contract.code_hash,
     }
-    .to_cosmos_msg(rng_hash, rng_addr.into_string(), None)?;
+    .to_cosmos_msg(state.rng_hash.clone(), rng_addr.into_string(), None)?;
 
-    Ok(Response::new().add_message(fulfill_rn_msg))
+    state.key_gen_status = KeyGenStatus::Fulfilled;
+    CONFIG.save(deps.storage, &state)?;
+
+    Ok(Response::new()
+        .add_message(fulfill_rn_msg)
+        .add_attribute_plaintext("key_gen_status", "fulfilled"))
 }
 
-fn create_gateway_keys(deps: DepsMut, env: Env, prng_seed: [u8; 32]) -> StdResult<Response> {
+fn create_gateway_keys(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    purpose: Option<String>,
+    prng_seed: [u8; 32],
+) -> StdResult<Response> {
     // load config
-    let state = CONFIG.load(deps.storage)?;
+    let mut state = CONFIG.load(deps.storage)?;
+
+    // only accept randomness from the configured scrt-rng contract
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != state.rng_addr {
+        return Err(StdError::generic_err(
+            "randomness must come from the configured scrt-rng contract",
+        ));
+    }
+    if purpose.as_deref() != Some(RNG_PURPOSE) {
+        return Err(StdError::generic_err("unexpected randomness purpose"));
+    }
 
     // check if the keys have already been created
-    if state.keyed {
+    if state.key_gen_status == KeyGenStatus::Keyed {
         return Err(StdError::generic_err(
             "keys have already been created".to_string(),
         ));
     }
 
+    // the callback is only expected after `KeyGen` asked scrt-rng for it
+    if state.key_gen_status != KeyGenStatus::Fulfilled {
+        return Err(StdError::generic_err("randomness has not been requested"));
+    }
+
+    // mix the previous seed into the new randomness when rotating keys
+    let prng_seed = match PRNG_SEED.may_load(deps.storage)? {
+        Some(previous_seed) => new_entropy(&env, &prng_seed, &previous_seed).to_vec(),
+        None => prng_seed.to_vec(),
+    };
+
     // Generate secp256k1 key pair for encryption
-    let (secret, public, new_prng_seed) = generate_keypair(&env, prng_seed.to_vec(), None)?;
+    let (secret, public, new_prng_seed) = generate_keypair(&env, prng_seed, None)?;
     let encryption_keys = KeyPair {
         sk: Binary(secret.serialize().to_vec()), // private key is 32 bytes,
         pk: Binary(public.serialize_compressed().to_vec()), // public key is 33 bytes
@@ -165,406 +593,4949 @@ fn create_gateway_keys(deps: DepsMut, env: Env, prng_seed: [u8; 32]) -> StdResul
         pk: Binary(public.serialize().to_vec()), // public key is 65 bytes
     };
 
-    CONFIG.update(deps.storage, |mut state| {
-        state.keyed = true;
-        state.encryption_keys = encryption_keys.clone();
-        state.signing_keys = signing_keys.clone();
-        Ok(state)
-    })?;
+    // retire the current keys if this is a rotation
+    if state.keyed() {
+        retire_key_epoch(deps.storage, &env, &state)?;
+        state.key_epoch += 1;
+    }
+
+    state.key_gen_status = KeyGenStatus::Keyed;
+    state.encryption_keys = encryption_keys.clone();
+    state.signing_keys = signing_keys.clone();
+    CONFIG.save(deps.storage, &state)?;
 
-    PRNG_SEED.save(deps.storage, &new_prng_seed)?; // is there any need to save this?
+    PRNG_SEED.save(deps.storage, &new_prng_seed)?; // seeds the next key rotation
 
     let encryption_pubkey = encryption_keys.pk.to_base64();
     let signing_pubkey = signing_keys.pk.to_base64();
 
     Ok(Response::new()
+        .add_attribute_plaintext("key_gen_status", "keyed")
+        .add_attribute_plaintext("key_epoch", state.key_epoch.to_string())
         .add_attribute_plaintext("encryption_pubkey", encryption_pubkey)
         .add_attribute_plaintext("signing_pubkey", signing_pubkey))
 }
 
-fn pre_execution(deps: DepsMut, _env: Env, msg: PreExecutionMsg) -> StdResult<Response> {
-    // verify that signature is correct
-    msg.verify(&deps)?;
-
-    // load config
-    let config = CONFIG.load(deps.storage)?;
-
-    // decrypt payload
-    let payload = msg.decrypt_payload(config.encryption_keys.sk)?;
-    let input_values = payload.data;
-
-    // combine input values and task ID to create verification hash
-    let input_hash = sha_256(&[input_values.as_bytes(), &msg.task_id.to_le_bytes()].concat());
-
-    // verify the internal verification key matches the user address
-    if payload.user_key != msg.user_key {
-        return Err(StdError::generic_err("verification key mismatch"));
-    }
-    // verify the routing info matches the internally stored routing info
-    if msg.routing_info != payload.routing_info {
-        return Err(StdError::generic_err("routing info mismatch"));
+/// Stores the current key pairs as a retired epoch. Payloads encrypted to it are accepted, and the
+/// results of its tasks signed with its key, until the grace period passes. The epoch before it
+/// can no longer be used and loses its secret keys.
+fn retire_key_epoch(storage: &mut dyn Storage, env: &Env, state: &State) -> StdResult<()> {
+    if let Some(previous_epoch) = state.key_epoch.checked_sub(1) {
+        if let Some(mut retired) = KEY_HISTORY.get(storage, &previous_epoch) {
+            retired.encryption_keys = retired.encryption_keys.public_only();
+            retired.signing_keys = retired.signing_keys.public_only();
+            retired.expires_at = retired.expires_at.min(env.block.height);
+            KEY_HISTORY.insert(storage, &previous_epoch, &retired)?;
+        }
     }
 
-    // create a task information store
-    let task_info = TaskInfo {
-        payload: msg.payload, // storing the ENCRYPTED payload
-        payload_hash: msg.payload_hash,
-        input_hash, // storing the DECRYPTED input_values hashed together with task ID
-        source_network: msg.source_network,
-        user_address: payload.user_address.clone(),
+    let retired = KeyEpoch {
+        encryption_keys: state.encryption_keys.clone(),
+        signing_keys: state.signing_keys.clone(),
+        retired_at: env.block.height,
+        expires_at: env.block.height + state.key_grace_period,
     };
+    KEY_HISTORY.insert(storage, &state.key_epoch, &retired)
+}
 
-    // map task ID to task info
-    TASK_MAP.insert(deps.storage, &msg.task_id, &task_info)?;
+fn try_admin_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    action: AdminAction,
+) -> StdResult<Response> {
+    // load config
+    let state = CONFIG.load(deps.storage)?;
 
-    // load this gateway's signing key
-    let mut signing_key_bytes = [0u8; 32];
-    signing_key_bytes.copy_from_slice(config.signing_keys.sk.as_slice());
+    // while a council is set, sensitive actions can only run through approved proposals
+    if COUNCIL.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("action requires council approval"));
+    }
+    check_admin(deps.as_ref(), &info, &state)?;
 
-    // used in production to create signature
-    #[cfg(target_arch = "wasm32")]
-    let signature = deps
-        .api
-        .secp256k1_sign(&input_hash, &signing_key_bytes)
-        .map_err(|err| StdError::generic_err(err.to_string()))?;
-    // let signature = PrivateKey::parse(&signing_key_bytes)?
-    //     .sign(&input_hash, deps.api)
-    //     .serialize()
-    //     .to_vec();
+    execute_admin_action(deps, env, action)
+}
 
-    // used only in unit testing to create signatures
-    #[cfg(not(target_arch = "wasm32"))]
-    let signature = {
-        let secp = secp256k1::Secp256k1::signing_only();
-        let sk = secp256k1::SecretKey::from_slice(&signing_key_bytes).unwrap();
-        let message = secp256k1::Message::from_slice(&input_hash)
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
-        secp.sign_ecdsa(&message, &sk).serialize_compact().to_vec()
-    };
+fn try_propose(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    action: AdminAction,
+) -> StdResult<Response> {
+    // only council members can make proposals
+    let council = load_council(deps.storage)?;
+    let sender_raw = check_council_member(deps.as_ref(), &info, &council)?;
 
-    // construct the message to send to the destination contract
-    let private_contract_msg = SecretMsg::Input {
-        message: PrivContractHandleMsg {
-            input_values,
-            handle: msg.handle,
-            user_address: payload.user_address,
-            task_id: msg.task_id,
-            input_hash: Binary(input_hash.to_vec()),
-            signature: Binary(signature),
-        },
+    // proposal IDs are never reused since proposals are kept after execution
+    let proposal_id = PROPOSALS.get_len(deps.storage)? as u64;
+    let proposal = Proposal {
+        action,
+        approvals: vec![sender_raw],
+        executed: false,
     };
-    let cosmos_msg = private_contract_msg.to_cosmos_msg(
-        msg.routing_code_hash,
-        msg.routing_info.into_string(),
-        None,
-    )?;
 
-    Ok(Response::new()
-        .add_message(cosmos_msg)
-        .add_attribute_plaintext("task_id", msg.task_id.to_string())
-        .add_attribute_plaintext("status", "sent to private contract")
-        .set_data(to_binary(&InputResponse { status: Success })?))
+    approve_proposal(deps, env, &council, proposal_id, proposal)
 }
 
-fn post_execution(deps: DepsMut, _env: Env, msg: PostExecutionMsg) -> StdResult<Response> {
-    // load task info and remove task ID from map
-    let task_info = TASK_MAP
-        .get(deps.storage, &msg.task_id)
-        .ok_or_else(|| StdError::generic_err("task id not found"))?;
-
-    // this panics in unit tests
-    #[cfg(target_arch = "wasm32")]
-    TASK_MAP.remove(deps.storage, &msg.task_id)?;
+fn try_approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: u64,
+) -> StdResult<Response> {
+    // only council members can approve proposals
+    let council = load_council(deps.storage)?;
+    let sender_raw = check_council_member(deps.as_ref(), &info, &council)?;
 
-    // verify that input hash is correct one for Task ID
-    if msg.input_hash.as_slice() != task_info.input_hash.to_vec() {
-        return Err(StdError::generic_err("input hash does not match task id"));
+    let mut proposal = PROPOSALS
+        .get(deps.storage, &proposal_id)
+        .ok_or_else(|| StdError::generic_err("proposal not found"))?;
+    if proposal_id < council.first_proposal {
+        return Err(StdError::generic_err(
+            "proposal was made to a previous council",
+        ));
     }
+    if proposal.executed {
+        return Err(StdError::generic_err("proposal has already been executed"));
+    }
+    if proposal.approvals.contains(&sender_raw) {
+        return Err(StdError::generic_err("proposal has already been approved"));
+    }
+    proposal.approvals.push(sender_raw);
 
-    // rename for clarity (original source network is now the routing destination)
-    let routing_info = task_info.source_network;
-
-    // "hasher" is used to perform multiple Keccak256 hashes
-    let mut hasher = Keccak256::new();
-
-    // requirement of Ethereum's `ecrecover` function
-    let prefix = "\x19Ethereum Signed Message:\n32".as_bytes();
+    approve_proposal(deps, env, &council, proposal_id, proposal)
+}
 
-    // the first hash guarantees the message lenth is 32
-    // the second hash prepends the Ethereum message
+/// Returns StdResult<Response>
+///
+/// saves a proposal and executes its action once it has reached the council threshold.
+///
+/// # Arguments
+///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `council` - the council the proposal was made to
+/// * `proposal_id` - ID of the proposal
+/// * `proposal` - the proposal including the latest approval
+fn approve_proposal(
+    deps: DepsMut,
+    env: Env,
+    council: &Council,
+    proposal_id: u64,
+    mut proposal: Proposal,
+) -> StdResult<Response> {
+    let approvals = proposal.approvals.len();
+    let response = if approvals >= council.threshold as usize {
+        proposal.executed = true;
+        PROPOSALS.insert(deps.storage, &proposal_id, &proposal)?;
+        execute_admin_action(deps, env, proposal.action)?
+    } else {
+        PROPOSALS.insert(deps.storage, &proposal_id, &proposal)?;
+        Response::new()
+    };
 
-    // create message hash of (result + payload + inputs)
-    let data = [
-        msg.result.as_bytes(),
-        task_info.payload.as_slice(),
-        &task_info.input_hash,
-    ]
-    .concat();
-    hasher.update(&data);
-    let result_hash = hasher.finalize_reset();
-    hasher.update([prefix, &result_hash].concat());
-    let result_hash = hasher.finalize_reset();
+    Ok(response
+        .add_attribute_plaintext("proposal_id", proposal_id.to_string())
+        .add_attribute_plaintext("approvals", approvals.to_string())
+        .add_attribute_plaintext("executed", proposal.executed.to_string()))
+}
 
-    // load this gateway's signing key
-    let private_key = CONFIG.load(deps.storage)?.signing_keys.sk;
-    let mut signing_key_bytes = [0u8; 32];
-    signing_key_bytes.copy_from_slice(private_key.as_slice());
+fn execute_admin_action(deps: DepsMut, env: Env, action: AdminAction) -> StdResult<Response> {
+    match action {
+        AdminAction::RotateKeys { entropy } => rotate_keys(deps, env, entropy),
+        AdminAction::Pause { scope } => set_paused(deps, scope, true),
+        AdminAction::Unpause { scope } => set_paused(deps, scope, false),
+        AdminAction::ProposeAdmin { new_admin } => propose_admin(deps, new_admin),
+        AdminAction::SetCouncil { members, threshold } => set_council(deps, members, threshold),
+        AdminAction::SetRoute {
+            routing_info,
+            code_hash,
+            handles,
+        } => set_route(deps, routing_info, code_hash, handles),
+        AdminAction::RemoveRoute { routing_info } => remove_route(deps, routing_info),
+        AdminAction::SetSigningScheme { network, scheme } => {
+            set_signing_scheme(deps, network, scheme)
+        }
+        AdminAction::SetKeyGracePeriod { blocks } => set_key_grace_period(deps, blocks),
+        AdminAction::RevokeKeyEpoch { epoch } => revoke_key_epoch(deps, env, epoch),
+        AdminAction::SetTaskTimeout { blocks } => set_task_timeout(deps, blocks),
+        AdminAction::SetRouteTimeout {
+            routing_info,
+            blocks,
+        } => set_route_timeout(deps, routing_info, blocks),
+        AdminAction::SetRouteFee {
+            routing_info,
+            min_fee,
+        } => set_route_fee(deps, routing_info, min_fee),
+    }
+}
 
-    // used in production to create signatures
-    // NOTE: api.secp256k1_sign() will perform an additional sha_256 hash operation on the given data
-    #[cfg(target_arch = "wasm32")]
-    let result_signature = {
-        // let sk = PrivateKey::parse(&signing_key_bytes)?;
-        // let result_signature = sk.sign(&result_hash, deps.api).serialize().to_vec();
+fn propose_admin(deps: DepsMut, new_admin: Addr) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
 
-        let result_signature = deps
-            .api
-            .secp256k1_sign(&result_hash, &signing_key_bytes)
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
+    // the handover completes once the new admin accepts
+    let new_admin = deps.api.addr_validate(new_admin.as_str())?;
+    state.pending_admin = Some(deps.api.addr_canonicalize(new_admin.as_str())?);
+    CONFIG.save(deps.storage, &state)?;
 
-        result_signature
-    };
+    Ok(Response::new().add_attribute_plaintext("pending_admin", new_admin))
+}
 
-    // used only in unit testing to create signatures
-    #[cfg(not(target_arch = "wasm32"))]
-    let result_signature = {
-        let secp = secp256k1::Secp256k1::signing_only();
-        let sk = secp256k1::SecretKey::from_slice(&signing_key_bytes).unwrap();
+fn try_accept_admin(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
 
-        let result_message = secp256k1::Message::from_slice(&result_hash)
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
-        let result_signature = secp
-            .sign_ecdsa_recoverable(&result_message, &sk)
-            .serialize_compact();
+    // only the nominated address can complete the handover
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if state.pending_admin.as_ref() != Some(&sender_raw) {
+        return Err(StdError::generic_err("sender is not the pending admin"));
+    }
 
-        result_signature.1
-    };
+    state.admin = sender_raw;
+    state.pending_admin = None;
+    CONFIG.save(deps.storage, &state)?;
 
-    // create hash of entire packet (used to verify the message wasn't modified in transit)
-    let data = [
-        "secret".as_bytes(),               // source network
-        routing_info.as_bytes(),           // task_destination_network
-        &msg.task_id.to_le_bytes(),        // task ID
-        task_info.payload.as_slice(),      // payload (original encrypted payload)
-        task_info.payload_hash.as_slice(), // original payload message
-        msg.result.as_bytes(),             // result
-        &result_hash,                      // result message
-        &result_signature,                 // result signature
-    ]
-    .concat();
-    hasher.update(&data);
-    let packet_hash = hasher.finalize_reset();
-    hasher.update([prefix, &packet_hash].concat());
-    let packet_hash = hasher.finalize();
+    Ok(Response::new().add_attribute_plaintext("admin", info.sender))
+}
 
-    // used in production to create signature
-    // NOTE: api.secp256k1_sign() will perform an additional sha_256 hash operation on the given data
-    #[cfg(target_arch = "wasm32")]
-    let packet_signature = {
-        deps.api
-            .secp256k1_sign(&packet_hash, &signing_key_bytes)
-            .map_err(|err| StdError::generic_err(err.to_string()))?
-    };
-    // let packet_signature = {
-    //     PrivateKey::parse(&signing_key_bytes)?
-    //         .sign(&packet_hash, deps.api)
-    //         .serialize()
-    //         .to_vec()
-    // };
-
-    // used only in unit testing to create signature
-    #[cfg(not(target_arch = "wasm32"))]
-    let packet_signature = {
-        let secp = secp256k1::Secp256k1::signing_only();
-        let sk = secp256k1::SecretKey::from_slice(&signing_key_bytes).unwrap();
+fn set_council(deps: DepsMut, members: Vec<Addr>, threshold: u32) -> StdResult<Response> {
+    // an empty council hands sensitive actions back to the admin
+    if members.is_empty() {
+        COUNCIL.remove(deps.storage);
+        return Ok(Response::new().add_attribute_plaintext("council_members", "0"));
+    }
 
-        let packet_message = secp256k1::Message::from_slice(&sha_256(&packet_hash))
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let mut members_raw: Vec<CanonicalAddr> = vec![];
+    for member in members {
+        let member = deps.api.addr_validate(member.as_str())?;
+        let member_raw = deps.api.addr_canonicalize(member.as_str())?;
+        if !members_raw.contains(&member_raw) {
+            members_raw.push(member_raw);
+        }
+    }
+    if threshold == 0 || threshold as usize > members_raw.len() {
+        return Err(StdError::generic_err(
+            "threshold must be between 1 and the number of council members",
+        ));
+    }
 
-        secp.sign_ecdsa(&packet_message, &sk).serialize_compact()
+    // proposals made before this point can no longer be approved
+    let council = Council {
+        members: members_raw,
+        threshold,
+        first_proposal: PROPOSALS.get_len(deps.storage)? as u64,
     };
+    COUNCIL.save(deps.storage, &council)?;
 
-    // convert the hashes and signatures into hex byte strings
-    // NOTE: we need to perform the additional sha_256 because that is what the secret network API method does
-    // NOTE: we add an extra byte to the end of the signatures for `ecrecover` in Solidity
-    // let task_id = format!("{:#04x}", &msg.task_id);
-    let payload_hash = format!(
-        "0x{}",
-        task_info.payload_hash.as_slice().encode_hex::<String>()
-    );
-    let result = format!("0x{}", msg.result.encode_hex::<String>());
-    let result_hash = format!("0x{}", sha_256(&result_hash).encode_hex::<String>());
-    let result_signature = format!("0x{}{:x}", &result_signature.encode_hex::<String>(), 27);
-    let packet_hash = format!("0x{}", sha_256(&packet_hash).encode_hex::<String>());
-    let packet_signature = format!("0x{}{:x}", &packet_signature.encode_hex::<String>(), 27);
+    Ok(Response::new()
+        .add_attribute_plaintext("council_members", council.members.len().to_string())
+        .add_attribute_plaintext("council_threshold", threshold.to_string()))
+}
+
+fn rotate_keys(deps: DepsMut, env: Env, entropy: String) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
+
+    // a rotation needs existing keys and no other key generation in progress
+    if !state.keyed() {
+        return Err(StdError::generic_err("gateway keys have not been created"));
+    }
+    if state.key_gen_status != KeyGenStatus::Keyed {
+        return Err(StdError::generic_err(
+            "key generation is already in progress",
+        ));
+    }
+
+    let rng_addr = deps.api.addr_humanize(&state.rng_addr)?;
+    let rng_msg = request_randomness(env, entropy, state.rng_hash.clone(), rng_addr)?;
+
+    state.key_gen_status = KeyGenStatus::Requested;
+    CONFIG.save(deps.storage, &state)?;
 
     Ok(Response::new()
-        .add_attribute_plaintext("source_network", "secret")
-        .add_attribute_plaintext("task_destination_network", routing_info)
-        .add_attribute_plaintext("task_id", msg.task_id.to_string())
-        .add_attribute_plaintext("payload_hash", payload_hash)
-        .add_attribute_plaintext("result", result)
-        .add_attribute_plaintext("result_hash", result_hash)
-        .add_attribute_plaintext("result_signature", result_signature)
-        .add_attribute_plaintext("packet_hash", packet_hash)
-        .add_attribute_plaintext("packet_signature", packet_signature))
+        .add_message(rng_msg)
+        .add_attribute_plaintext("key_gen_status", "requested"))
 }
 
-#[cfg(feature = "contract")]
-/////////////////////////////////////// Query /////////////////////////////////////
-/// Returns QueryResult
-///
-/// # Arguments
-///
-/// * `deps` - reference to Extern containing all the contract's external dependencies
-/// * `msg` - QueryMsg passed in with the query call
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    let response = match msg {
-        QueryMsg::GetPublicKeys {} => query_public_keys(deps),
-    };
-    pad_query_result(response, BLOCK_SIZE)
+fn set_key_grace_period(deps: DepsMut, blocks: u64) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
+
+    state.key_grace_period = blocks;
+    CONFIG.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attribute_plaintext("key_grace_period", blocks.to_string()))
 }
 
-// the encryption key will be a base64 string, the verifying key will be a '0x' prefixed hex string
-fn query_public_keys(deps: Deps) -> StdResult<Binary> {
-    let state: State = CONFIG.load(deps.storage)?;
-    to_binary(&PublicKeyResponse {
-        encryption_key: state.encryption_keys.pk,
-        verification_key: format!(
-            "0x{}",
-            state.signing_keys.pk.as_slice().encode_hex::<String>()
-        ),
-    })
+fn revoke_key_epoch(deps: DepsMut, env: Env, epoch: u32) -> StdResult<Response> {
+    // the current epoch must be rotated out before it can be revoked
+    let mut retired = KEY_HISTORY
+        .get(deps.storage, &epoch)
+        .ok_or_else(|| StdError::generic_err("key epoch is not retired"))?;
+
+    retired.encryption_keys = retired.encryption_keys.public_only();
+    retired.signing_keys = retired.signing_keys.public_only();
+    retired.expires_at = retired.expires_at.min(env.block.height);
+    KEY_HISTORY.insert(deps.storage, &epoch, &retired)?;
+
+    Ok(Response::new().add_attribute_plaintext("revoked_key_epoch", epoch.to_string()))
 }
 
-/////////////////////////////////////// Helpers /////////////////////////////////////
+fn try_set_relayers(
+    deps: DepsMut,
+    info: MessageInfo,
+    relayers: Vec<Addr>,
+    registered: bool,
+) -> StdResult<Response> {
+    // load config
+    let state = CONFIG.load(deps.storage)?;
 
-/// Returns (PublicKey, StaticSecret, Vec<u8>)
-///
-/// generates a public and privite key pair and generates a new PRNG_SEED with or without user entropy.
-///
-/// # Arguments
-///
-/// * `env` - contract's environment to be used for randomization
-/// * `prng_seed` - required prng seed for randomization
-/// * `user_entropy` - optional random string input by the user
-pub fn generate_keypair(
-    env: &Env,
-    prng_seed: Vec<u8>,
-    user_entropy: Option<String>,
-) -> Result<(PrivateKey, PublicKey, Vec<u8>), StdError> {
-    // generate new rng seed
-    let new_prng_bytes: [u8; 32] = match user_entropy {
-        Some(s) => new_entropy(env, prng_seed.as_ref(), s.as_bytes()),
-        None => new_entropy(env, prng_seed.as_ref(), prng_seed.as_ref()),
+    // only the admin can edit the relayer registry
+    check_admin(deps.as_ref(), &info, &state)?;
+
+    for relayer in relayers {
+        let relayer = deps.api.addr_validate(relayer.as_str())?;
+        let mut relayer_info = RELAYERS.get(deps.storage, &relayer).unwrap_or_default();
+        relayer_info.registered = registered;
+        RELAYERS.insert(deps.storage, &relayer, &relayer_info)?;
+    }
+
+    Ok(Response::new())
+}
+
+fn try_set_permissionless(
+    deps: DepsMut,
+    info: MessageInfo,
+    permissionless: bool,
+) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
+
+    // only the admin can open or close the gateway to unregistered relayers
+    check_admin(deps.as_ref(), &info, &state)?;
+
+    state.permissionless = permissionless;
+    CONFIG.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attribute_plaintext("permissionless", permissionless.to_string()))
+}
+
+fn set_task_timeout(deps: DepsMut, blocks: u64) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
+
+    state.task_timeout = blocks;
+    CONFIG.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_attribute_plaintext("task_timeout", blocks.to_string()))
+}
+
+fn set_route_timeout(deps: DepsMut, routing_info: Addr, blocks: u64) -> StdResult<Response> {
+    let routing_info = deps.api.addr_validate(routing_info.as_str())?;
+    ROUTE_TIMEOUTS.insert(deps.storage, &routing_info, &blocks)?;
+
+    Ok(Response::new()
+        .add_attribute_plaintext("routing_info", routing_info)
+        .add_attribute_plaintext("task_timeout", blocks.to_string()))
+}
+
+fn set_route_fee(deps: DepsMut, routing_info: Addr, min_fee: Uint128) -> StdResult<Response> {
+    let routing_info = deps.api.addr_validate(routing_info.as_str())?;
+    ROUTE_FEES.insert(deps.storage, &routing_info, &min_fee)?;
+
+    Ok(Response::new()
+        .add_attribute_plaintext("routing_info", routing_info)
+        .add_attribute_plaintext("min_fee", min_fee.to_string()))
+}
+
+fn try_claim_fees(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
+    let mut relayer_info = RELAYERS.get(deps.storage, &info.sender).unwrap_or_default();
+    if relayer_info.claimable_fees.is_zero() {
+        return Err(StdError::generic_err("no fees to claim"));
+    }
+
+    let amount = relayer_info.claimable_fees;
+    relayer_info.claimable_fees = Uint128::zero();
+    RELAYERS.insert(deps.storage, &info.sender, &relayer_info)?;
+
+    Ok(Response::new()
+        .add_message(BankMsg::Send {
+            to_address: info.sender.into_string(),
+            amount: coins(amount.u128(), FEE_DENOM),
+        })
+        .add_attribute_plaintext("claimed_fees", amount.to_string()))
+}
+
+fn set_paused(deps: DepsMut, scope: PauseScope, paused: bool) -> StdResult<Response> {
+    // load config
+    let mut state = CONFIG.load(deps.storage)?;
+
+    let response = Response::new().add_attribute_plaintext("paused", paused.to_string());
+    let response = match scope {
+        PauseScope::All => {
+            state.paused = paused;
+            CONFIG.save(deps.storage, &state)?;
+            response.add_attribute_plaintext("pause_scope", "all")
+        }
+        PauseScope::Network { network } => {
+            PAUSED_NETWORKS.insert(deps.storage, &network, &paused)?;
+            response
+                .add_attribute_plaintext("pause_scope", "network")
+                .add_attribute_plaintext("network", network)
+        }
+        PauseScope::Route { routing_info } => {
+            let routing_info = deps.api.addr_validate(routing_info.as_str())?;
+            PAUSED_ROUTES.insert(deps.storage, &routing_info, &paused)?;
+            response
+                .add_attribute_plaintext("pause_scope", "route")
+                .add_attribute_plaintext("routing_info", routing_info)
+        }
     };
 
-    // generate and return key pair
-    let mut rng = Prng::new(prng_seed.as_ref(), new_prng_bytes.as_ref());
-    let sk = PrivateKey::parse(&rng.rand_bytes())?;
-    let pk = sk.pubkey();
+    Ok(response)
+}
 
-    Ok((sk, pk, new_prng_bytes.to_vec()))
+fn set_route(
+    deps: DepsMut,
+    routing_info: Addr,
+    code_hash: String,
+    handles: Vec<String>,
+) -> StdResult<Response> {
+    let routing_info = deps.api.addr_validate(routing_info.as_str())?;
+    if handles.is_empty() {
+        return Err(StdError::generic_err("a route needs at least one handle"));
+    }
+
+    ROUTES.insert(
+        deps.storage,
+        &routing_info,
+        &RouteInfo {
+            code_hash: code_hash.clone(),
+            handles,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute_plaintext("route_registered", routing_info)
+        .add_attribute_plaintext("code_hash", code_hash))
 }
 
-/// Returns [u8;32]
+fn remove_route(deps: DepsMut, routing_info: Addr) -> StdResult<Response> {
+    let mut route = ROUTES
+        .get(deps.storage, &routing_info)
+        .filter(|route| !route.handles.is_empty())
+        .ok_or_else(|| {
+            StdError::generic_err(format!("route {} is not registered", routing_info))
+        })?;
+
+    // removed routes are kept in storage without handles
+    route.handles = vec![];
+    ROUTES.insert(deps.storage, &routing_info, &route)?;
+
+    Ok(Response::new().add_attribute_plaintext("route_removed", routing_info))
+}
+
+fn set_signing_scheme(
+    deps: DepsMut,
+    network: String,
+    scheme: SigningScheme,
+) -> StdResult<Response> {
+    // a malformed domain would leave every packet to the network unverifiable
+    if let SigningScheme::Eip712 {
+        verifying_contract, ..
+    } = &scheme
+    {
+        evm_address(verifying_contract)?;
+    }
+
+    SIGNING_SCHEMES.insert(deps.storage, &network, &scheme)?;
+
+    Ok(Response::new().add_attribute_plaintext("signing_scheme_set", network))
+}
+
+fn pre_execution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: PreExecutionMsg,
+) -> StdResult<Response> {
+    let task_id = msg.task_id;
+
+    // the attached uscrt is the task fee
+    let fee = attached_fee(&info)?;
+    let sub_msg = accept_task(deps, &env, &info.sender, msg, fee, false)?;
+
+    Ok(Response::new()
+        .add_submessage(sub_msg)
+        .add_attribute_plaintext("task_id", task_id.to_string())
+        .add_attribute_plaintext("status", "sent to private contract")
+        .set_data(to_binary(&InputResponse { status: Success })?))
+}
+
+fn pre_execution_batch(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    inputs: Vec<PreExecutionMsg>,
+) -> StdResult<Response> {
+    if inputs.is_empty() {
+        return Err(StdError::generic_err("batch has no inputs"));
+    }
+
+    // the attached uscrt pays the route minimum of each task in order, the rest is returned
+    let mut unspent = attached_fee(&info)?;
+
+    // a rejected task is reported in the results instead of failing the whole batch
+    let mut sub_msgs = vec![];
+    let mut results = vec![];
+    for msg in inputs {
+        let source_network = msg.source_network.clone();
+        let task_id = msg.task_id;
+        let min_fee = ROUTE_FEES
+            .get(deps.storage, &msg.routing_info)
+            .unwrap_or_default();
+        let fee = min_fee.min(unspent);
+
+        let result = match accept_task(deps.branch(), &env, &info.sender, msg, fee, true) {
+            Ok(sub_msg) => {
+                unspent -= fee;
+                sub_msgs.push(sub_msg);
+                BatchInputResult {
+                    source_network,
+                    task_id,
+                    status: Success,
+                    error: None,
+                }
+            }
+            Err(err) => BatchInputResult {
+                source_network,
+                task_id,
+                status: Failure,
+                error: Some(err.to_string()),
+            },
+        };
+        results.push(result);
+    }
+
+    let accepted_tasks = sub_msgs.len();
+    let rejected_tasks = results.len() - accepted_tasks;
+    let mut response = Response::new().add_submessages(sub_msgs);
+    if !unspent.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: info.sender.into_string(),
+            amount: coins(unspent.u128(), FEE_DENOM),
+        });
+    }
+
+    Ok(response
+        .add_attribute_plaintext("accepted_tasks", accepted_tasks.to_string())
+        .add_attribute_plaintext("rejected_tasks", rejected_tasks.to_string())
+        .set_data(to_binary(&InputBatchResponse { results })?))
+}
+
+/// Returns StdResult<SubMsg>
 ///
-/// generates new entropy from block data, does not save it to the contract.
+/// validates a task, stores it and builds the call to its private contract. Nothing is written
+/// to storage unless the task is accepted.
 ///
 /// # Arguments
 ///
+/// * `deps` - mutable reference to Extern containing all the contract's external dependencies
 /// * `env` - Env of contract's environment
-/// * `seed` - (user generated) seed for rng
-/// * `entropy` - Entropy seed saved in the contract
-pub fn new_entropy(env: &Env, seed: &[u8], entropy: &[u8]) -> [u8; 32] {
-    // 16 here represents the lengths in bytes of the block height and time.
-    let entropy_len = 16 + env.contract.address.to_string().len() + entropy.len();
-    let mut rng_entropy = Vec::with_capacity(entropy_len);
-    rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
-    rng_entropy.extend_from_slice(&env.block.time.seconds().to_be_bytes());
-    rng_entropy.extend_from_slice(env.contract.address.to_string().as_bytes());
-    rng_entropy.extend_from_slice(entropy);
+/// * `relayer` - sender of the `Input`
+/// * `msg` - the relayed task
+/// * `fee` - uscrt paid for the task
+/// * `batched` - whether the task is part of an `InputBatch`
+fn accept_task(
+    deps: DepsMut,
+    env: &Env,
+    relayer: &Addr,
+    msg: PreExecutionMsg,
+    fee: Uint128,
+    batched: bool,
+) -> StdResult<SubMsg> {
+    // load config
+    let mut config = CONFIG.load(deps.storage)?;
 
-    let mut rng = Prng::new(seed, &rng_entropy);
+    // routes are stored under validated addresses
+    let routing_info = deps.api.addr_validate(msg.routing_info.as_str())?;
+
+    // only registered relayers can submit tasks unless the gateway is permissionless
+    let mut relayer_info = RELAYERS.get(deps.storage, relayer).unwrap_or_default();
+    if !config.permissionless && !relayer_info.registered {
+        return Err(StdError::generic_err("sender is not a registered relayer"));
+    }
+
+    // reject new tasks while the gateway, the source network or the route is paused
+    if config.paused {
+        return Err(StdError::generic_err("gateway is paused"));
+    }
+    if PAUSED_NETWORKS
+        .get(deps.storage, &msg.source_network)
+        .unwrap_or(false)
+    {
+        return Err(StdError::generic_err(format!(
+            "source network {} is paused",
+            msg.source_network
+        )));
+    }
+    if PAUSED_ROUTES
+        .get(deps.storage, &routing_info)
+        .unwrap_or(false)
+    {
+        return Err(StdError::generic_err(format!(
+            "route {} is paused",
+            routing_info
+        )));
+    }
+
+    // only registered private contracts and handles can be called
+    let route = ROUTES
+        .get(deps.storage, &routing_info)
+        .filter(|route| !route.handles.is_empty())
+        .ok_or_else(|| {
+            StdError::generic_err(format!("route {} is not registered", routing_info))
+        })?;
+    if !route.code_hash.eq_ignore_ascii_case(&msg.routing_code_hash) {
+        return Err(StdError::generic_err(
+            "routing code hash does not match the registered route",
+        ));
+    }
+    if !route.handles.contains(&msg.handle) {
+        return Err(StdError::generic_err(format!(
+            "handle {} is not allowed for route {}",
+            msg.handle, routing_info
+        )));
+    }
+
+    // the task fee has to cover the route minimum
+    let min_fee = ROUTE_FEES
+        .get(deps.storage, &routing_info)
+        .unwrap_or_default();
+    if fee < min_fee {
+        return Err(StdError::generic_err(format!(
+            "task fee of {}{} is below the route minimum of {}{}",
+            fee, FEE_DENOM, min_fee, FEE_DENOM
+        )));
+    }
+
+    // verify that signature is correct
+    msg.verify(&deps)?;
+
+    // tasks cannot be processed until the gateway has keys
+    if !config.keyed() {
+        return Err(StdError::generic_err("gateway keys have not been created"));
+    }
+
+    // tasks are identified by their source network and task ID, which can only be used once
+    let task_key: TaskKey = (msg.source_network.clone(), msg.task_id);
+    check_task_not_closed(deps.storage, &task_key)?;
+    if TASK_MAP.contains(deps.storage, &task_key) {
+        return Err(StdError::generic_err("task is already pending"));
+    }
+
+    // a nonce can only be used once per user key, reuse breaks the payload encryption and would
+    // let a captured payload be replayed under another task ID
+    let nonce_key = sha_256(&[msg.user_key.as_slice(), msg.nonce.as_slice()].concat());
+    if SEEN_NONCES.contains(deps.storage, &nonce_key) {
+        return Err(StdError::generic_err(
+            "nonce has already been used with this user key",
+        ));
+    }
+
+    // decrypt payload
+    let (payload, key_epoch, encryption_key) =
+        decrypt_with_key_epochs(deps.as_ref(), env, &config, &msg)?;
+    let input_values = payload.data;
+
+    // results are encrypted with the same shared key as the payload, if the user asked for it
+    let result_key = match payload.encrypt_result {
+        true => Some(msg.payload_key(encryption_key)?),
+        false => None,
+    };
+
+    // combine input values and task ID to create verification hash
+    let input_hash = sha_256(&[input_values.as_bytes(), &msg.task_id.to_le_bytes()].concat());
+
+    // verify the internal verification key matches the user address
+    if payload.user_key != msg.user_key {
+        return Err(StdError::generic_err("verification key mismatch"));
+    }
+    // verify the routing info matches the internally stored routing info
+    if routing_info != payload.routing_info {
+        return Err(StdError::generic_err("routing info mismatch"));
+    }
+
+    // a failing private contract call is answered with a failure result instead of reverting
+    let reply_id = config.tx_cnt + 1;
+
+    // create a task information store
+    let task_info = TaskInfo {
+        payload: msg.payload, // storing the ENCRYPTED payload
+        payload_hash: msg.payload_hash,
+        input_hash, // storing the DECRYPTED input_values hashed together with task ID
+        source_network: msg.source_network.clone(),
+        user_address: payload.user_address.clone(),
+        routing_info: routing_info.clone(),
+        routing_code_hash: msg.routing_code_hash.clone(),
+        key_epoch,
+        relayer: relayer.clone(),
+        accepted_height: env.block.height,
+        accepted_time: env.block.time.seconds(),
+        expires_at: env.block.height + task_timeout(deps.storage, &config, &routing_info),
+        fee,
+        result_key,
+        payload_version: msg.payload_version,
+        reply_id: Some(reply_id),
+    };
+
+    // load this gateway's signing key
+    let mut signing_key_bytes = [0u8; 32];
+    signing_key_bytes.copy_from_slice(config.signing_keys.sk.as_slice());
+
+    // used in production to create signature
+    #[cfg(target_arch = "wasm32")]
+    let signature = deps
+        .api
+        .secp256k1_sign(&input_hash, &signing_key_bytes)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    // let signature = PrivateKey::parse(&signing_key_bytes)?
+    //     .sign(&input_hash, deps.api)
+    //     .serialize()
+    //     .to_vec();
+
+    // used only in unit testing to create signatures
+    #[cfg(not(target_arch = "wasm32"))]
+    let signature = {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let sk = secp256k1::SecretKey::from_slice(&signing_key_bytes).unwrap();
+        let message = secp256k1::Message::from_slice(&input_hash)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        secp.sign_ecdsa(&message, &sk).serialize_compact().to_vec()
+    };
+
+    // construct the message to send to the destination contract
+    let private_contract_msg = SecretMsg::Input {
+        message: PrivContractHandleMsg {
+            input_values,
+            handle: msg.handle,
+            user_address: payload.user_address,
+            source_network: msg.source_network,
+            task_id: msg.task_id,
+            input_hash: Binary(input_hash.to_vec()),
+            signature: Binary(signature),
+        },
+    };
+    let cosmos_msg = private_contract_msg.to_cosmos_msg(
+        msg.routing_code_hash,
+        routing_info.into_string(),
+        None,
+    )?;
+
+    let mut sub_msg = SubMsg::reply_on_error(cosmos_msg, reply_id);
+    if let Some(gas_limit) = payload.gas_limit {
+        sub_msg = sub_msg.with_gas_limit(gas_limit);
+    }
+
+    // map source network and task ID to task info
+    TASK_MAP.insert(deps.storage, &task_key, &task_info)?;
+    SEEN_NONCES.insert(deps.storage, &nonce_key, &key_epoch)?;
+
+    // the reply ID of the private contract call leads back to the task if the call fails
+    config.tx_cnt = reply_id;
+    CONFIG.save(deps.storage, &config)?;
+    REPLY_TASKS.insert(deps.storage, &reply_id, &ReplyTask { task_key, batched })?;
+    update_task_counters(deps.storage, |counters| counters.accepted += 1)?;
+
+    // count the task towards the relayer that submitted it
+    relayer_info.tasks_submitted += 1;
+    RELAYERS.insert(deps.storage, relayer, &relayer_info)?;
+
+    Ok(sub_msg)
+}
+
+/// Returns StdResult<Uint128>
+///
+/// adds up the uscrt attached to a message, rejecting any other denomination.
+///
+/// # Arguments
+///
+/// * `info` - MessageInfo of the message being handled
+fn attached_fee(info: &MessageInfo) -> StdResult<Uint128> {
+    let mut fee = Uint128::zero();
+    for coin in &info.funds {
+        if coin.denom != FEE_DENOM {
+            return Err(StdError::generic_err(format!(
+                "only {} is accepted as a task fee",
+                FEE_DENOM
+            )));
+        }
+        fee += coin.amount;
+    }
+    Ok(fee)
+}
+
+fn post_execution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: PostExecutionMsg,
+) -> StdResult<Response> {
+    // results can only be posted once per task, and not after it expired
+    let task_key: TaskKey = (msg.source_network.clone(), msg.task_id);
+    check_task_not_closed(deps.storage, &task_key)?;
+
+    // load task info
+    let task_info = TASK_MAP
+        .get(deps.storage, &task_key)
+        .ok_or_else(|| StdError::generic_err("task id not found"))?;
+
+    // only the private contract the task was routed to can post its results
+    if info.sender != task_info.routing_info {
+        return Err(StdError::generic_err(
+            "sender is not the private contract this task was routed to",
+        ));
+    }
+
+    // verify that input hash is correct one for Task ID
+    if msg.input_hash.as_slice() != task_info.input_hash.to_vec() {
+        return Err(StdError::generic_err("input hash does not match task id"));
+    }
+
+    // remove the task, it is no longer pending
+    TASK_MAP.remove(deps.storage, &task_key)?;
+    if let Some(reply_id) = task_info.reply_id {
+        REPLY_TASKS.remove(deps.storage, &reply_id)?;
+    }
+
+    let (attributes, output_hash) = signed_packet(
+        deps.as_ref(),
+        &env,
+        msg.task_id,
+        (&task_info).into(),
+        &msg.result,
+    )?;
+
+    // keep a record of the task so it cannot be submitted again
+    CLOSED_TASKS.insert(
+        deps.storage,
+        &task_key,
+        &ClosedTask {
+            outcome: TaskOutcome::Completed,
+            closed_at: env.block.height,
+            payload_hash: task_info.payload_hash.clone(),
+            output_hash,
+        },
+    )?;
+
+    // credit the completed task to the relayer that submitted it
+    let mut relayer_info: RelayerInfo = RELAYERS
+        .get(deps.storage, &task_info.relayer)
+        .unwrap_or_default();
+    relayer_info.tasks_completed += 1;
+    relayer_info.claimable_fees += task_info.fee;
+    RELAYERS.insert(deps.storage, &task_info.relayer, &relayer_info)?;
+    update_task_counters(deps.storage, |counters| counters.completed += 1)?;
+
+    Ok(Response::new().add_attributes(attributes))
+}
+
+fn outbound(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    destination_network: String,
+    result: String,
+) -> StdResult<Response> {
+    // load config
+    let mut config = CONFIG.load(deps.storage)?;
+
+    // only registered private contracts can start messages to public networks
+    let route = ROUTES.get(deps.storage, &info.sender);
+    if !matches!(route, Some(route) if !route.handles.is_empty()) {
+        return Err(StdError::generic_err(
+            "sender is not a registered private contract",
+        ));
+    }
+
+    // reject new messages while the gateway, the destination network or the route is paused
+    if config.paused {
+        return Err(StdError::generic_err("gateway is paused"));
+    }
+    if PAUSED_NETWORKS
+        .get(deps.storage, &destination_network)
+        .unwrap_or(false)
+    {
+        return Err(StdError::generic_err(format!(
+            "destination network {} is paused",
+            destination_network
+        )));
+    }
+    if PAUSED_ROUTES
+        .get(deps.storage, &info.sender)
+        .unwrap_or(false)
+    {
+        return Err(StdError::generic_err(format!(
+            "route {} is paused",
+            info.sender
+        )));
+    }
+
+    // packets cannot be signed until the gateway has keys
+    if !config.keyed() {
+        return Err(StdError::generic_err("gateway keys have not been created"));
+    }
+
+    // the gateway numbers outbound messages itself, apart from the task IDs of public networks
+    config.outbound_cnt += 1;
+    let outbound_id = config.outbound_cnt;
+    CONFIG.save(deps.storage, &config)?;
+
+    // the packet commits to the sending contract in place of a user payload
+    let payload = info.sender.as_bytes();
+    let payload_hash = sha_256(payload);
+    let input_hash = sha_256(&[result.as_bytes(), &outbound_id.to_le_bytes()].concat());
+    let task = PacketTask {
+        destination_network: &destination_network,
+        payload,
+        payload_hash: &payload_hash,
+        input_hash: &input_hash,
+        result_key: None,
+        payload_version: 1,
+        key_epoch: config.key_epoch,
+    };
+    let (attributes, _) = signed_packet(deps.as_ref(), &env, outbound_id, task, &result)?;
+
+    // relayers pass outbound packets to `receiveOutbound` instead of `postExecution`
+    Ok(Response::new()
+        .add_attributes(attributes)
+        .add_attribute_plaintext("packet_type", "outbound")
+        .add_attribute_plaintext("private_contract", info.sender))
+}
+
+fn expire_tasks(deps: DepsMut, env: Env, limit: Option<u32>) -> StdResult<Response> {
+    // collect the timed out tasks first, the task map cannot change while it is iterated
+    let limit = limit.unwrap_or(DEFAULT_EXPIRY_LIMIT) as usize;
+    let mut expired_tasks: Vec<(TaskKey, TaskInfo)> = vec![];
+    for item in TASK_MAP.iter(deps.storage)? {
+        if expired_tasks.len() >= limit {
+            break;
+        }
+        let (task_key, task_info) = item?;
+        if task_info.expires_at <= env.block.height {
+            expired_tasks.push((task_key, task_info));
+        }
+    }
+
+    // the public gateway receives a signed failure result in place of the missing output
+    let result = failure_result(FailureCode::Timeout, TIMEOUT_ERROR)?;
+
+    let mut events = vec![];
+    let mut refunds = vec![];
+    for (task_key, task_info) in &expired_tasks {
+        TASK_MAP.remove(deps.storage, task_key)?;
+        if let Some(reply_id) = task_info.reply_id {
+            REPLY_TASKS.remove(deps.storage, &reply_id)?;
+        }
+
+        let (attributes, output_hash) =
+            signed_packet(deps.as_ref(), &env, task_key.1, task_info.into(), &result)?;
+
+        CLOSED_TASKS.insert(
+            deps.storage,
+            task_key,
+            &ClosedTask {
+                outcome: TaskOutcome::Expired,
+                closed_at: env.block.height,
+                payload_hash: task_info.payload_hash.clone(),
+                output_hash,
+            },
+        )?;
+
+        events.push(Event::new("task_timeout").add_attributes(attributes));
+        refunds.extend(refund_fee(task_info));
+    }
+    update_task_counters(deps.storage, |counters| {
+        counters.expired += expired_tasks.len() as u64
+    })?;
+
+    Ok(Response::new()
+        .add_messages(refunds)
+        .add_events(events)
+        .add_attribute_plaintext("expired_tasks", expired_tasks.len().to_string()))
+}
+
+fn prune_nonces(deps: DepsMut, env: Env, limit: Option<u32>) -> StdResult<Response> {
+    // collect the prunable nonces first, the nonce map cannot change while it is iterated
+    let limit = limit.unwrap_or(DEFAULT_PRUNE_LIMIT) as usize;
+    let state = CONFIG.load(deps.storage)?;
+    let mut pruned: Vec<[u8; 32]> = vec![];
+    for item in SEEN_NONCES.iter(deps.storage)? {
+        if pruned.len() >= limit {
+            break;
+        }
+        let (nonce_key, key_epoch) = item?;
+        if !epoch_decrypts_payloads(deps.storage, &env, &state, key_epoch) {
+            pruned.push(nonce_key);
+        }
+    }
+
+    for nonce_key in &pruned {
+        SEEN_NONCES.remove(deps.storage, nonce_key)?;
+    }
+
+    Ok(Response::new().add_attribute_plaintext("pruned_nonces", pruned.len().to_string()))
+}
+
+fn private_call_failed(deps: DepsMut, env: Env, reply_id: u64) -> StdResult<Response> {
+    let ReplyTask { task_key, batched } = REPLY_TASKS
+        .get(deps.storage, &reply_id)
+        .ok_or_else(|| StdError::generic_err(format!("unknown reply ID {}", reply_id)))?;
+    let task_info = TASK_MAP
+        .get(deps.storage, &task_key)
+        .ok_or_else(|| StdError::generic_err("task id not found"))?;
+
+    TASK_MAP.remove(deps.storage, &task_key)?;
+    REPLY_TASKS.remove(deps.storage, &reply_id)?;
+
+    // the public gateway receives a signed failure result in place of the missing output
+    let result = failure_result(FailureCode::ExecutionFailed, EXECUTION_ERROR)?;
+    let (attributes, output_hash) = signed_packet(
+        deps.as_ref(),
+        &env,
+        task_key.1,
+        (&task_info).into(),
+        &result,
+    )?;
+
+    // keep a record of the task so it cannot be submitted again
+    CLOSED_TASKS.insert(
+        deps.storage,
+        &task_key,
+        &ClosedTask {
+            outcome: TaskOutcome::Failed,
+            closed_at: env.block.height,
+            payload_hash: task_info.payload_hash.clone(),
+            output_hash,
+        },
+    )?;
+    update_task_counters(deps.storage, |counters| counters.failed += 1)?;
+
+    let response = Response::new()
+        .add_messages(refund_fee(&task_info))
+        .add_attributes(attributes);
+
+    // the data of a reply replaces the data of the `Input`, which for a batch holds the results
+    // of every task
+    if batched {
+        return Ok(response);
+    }
+    Ok(response.set_data(to_binary(&InputResponse { status: Failure })?))
+}
+
+#[cfg(feature = "contract")]
+/////////////////////////////////////// Query /////////////////////////////////////
+/// Returns QueryResult
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `msg` - QueryMsg passed in with the query call
+#[entry_point]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    let response = match msg {
+        QueryMsg::GetPublicKeys {} => query_public_keys(deps),
+        QueryMsg::GetEpochPublicKeys { epoch } => query_epoch_public_keys(deps, epoch),
+        QueryMsg::GetKeyGenStatus {} => query_key_gen_status(deps),
+        QueryMsg::GetRelayers { page, page_size } => query_relayers(deps, page, page_size),
+        QueryMsg::GetPauseStatus {} => query_pause_status(deps),
+        QueryMsg::GetAdmin {} => query_admin(deps),
+        QueryMsg::GetConfig {} => query_config(deps),
+        QueryMsg::GetRoutes { page, page_size } => query_routes(deps, page, page_size),
+        QueryMsg::GetProposal { proposal_id } => query_proposal(deps, proposal_id),
+        QueryMsg::GetSigningScheme { network } => query_signing_scheme(deps, network),
+        QueryMsg::GetTaskStatus {
+            source_network,
+            task_id,
+        } => query_task_status(deps, source_network, task_id),
+        QueryMsg::GetPendingTasks {
+            source_network,
+            routing_info,
+            page,
+            page_size,
+        } => query_pending_tasks(deps, source_network, routing_info, page, page_size),
+    };
+    pad_query_result(response, BLOCK_SIZE)
+}
+
+// the encryption key will be a base64 string, the verifying key will be a '0x' prefixed hex string
+fn query_public_keys(deps: Deps) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    to_binary(&PublicKeyResponse {
+        encryption_key: state.encryption_keys.pk.clone(),
+        verification_key: format!(
+            "0x{}",
+            state.signing_keys.pk.as_slice().encode_hex::<String>()
+        ),
+        ed25519_verification_key: state.keyed().then(|| {
+            let sk = ed25519_zebra::SigningKey::from(ed25519_signing_key(&state.signing_keys.sk));
+            let vk = ed25519_zebra::VerificationKeyBytes::from(&sk);
+            format!("0x{}", vk.as_ref().encode_hex::<String>())
+        }),
+        key_epoch: state.key_epoch,
+        expires_at: None,
+    })
+}
+
+fn query_epoch_public_keys(deps: Deps, epoch: u32) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    if epoch == state.key_epoch {
+        return query_public_keys(deps);
+    }
+
+    let retired = KEY_HISTORY
+        .get(deps.storage, &epoch)
+        .ok_or_else(|| StdError::generic_err("unknown key epoch"))?;
+    to_binary(&PublicKeyResponse {
+        encryption_key: retired.encryption_keys.pk,
+        verification_key: format!(
+            "0x{}",
+            retired.signing_keys.pk.as_slice().encode_hex::<String>()
+        ),
+        ed25519_verification_key: None,
+        key_epoch: epoch,
+        expires_at: Some(retired.expires_at),
+    })
+}
+
+fn query_key_gen_status(deps: Deps) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    to_binary(&KeyGenStatusResponse {
+        status: state.key_gen_status,
+    })
+}
+
+fn query_relayers(deps: Deps, page: Option<u32>, page_size: Option<u32>) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    let relayers = RELAYERS
+        .paging(
+            deps.storage,
+            page.unwrap_or(0),
+            page_size.unwrap_or(DEFAULT_PAGE_SIZE),
+        )?
+        .into_iter()
+        .map(|(address, relayer_info)| RelayerStatus {
+            address,
+            registered: relayer_info.registered,
+            tasks_submitted: relayer_info.tasks_submitted,
+            tasks_completed: relayer_info.tasks_completed,
+            claimable_fees: relayer_info.claimable_fees,
+        })
+        .collect();
+
+    to_binary(&RelayersResponse {
+        permissionless: state.permissionless,
+        relayers,
+    })
+}
+
+fn query_config(deps: Deps) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    let counters = TASK_COUNTERS.load(deps.storage)?;
+
+    // unpaused entries are kept in storage with a `false` flag
+    let mut paused_networks = 0;
+    for item in PAUSED_NETWORKS.iter(deps.storage)? {
+        if item?.1 {
+            paused_networks += 1;
+        }
+    }
+    let mut paused_routes = 0;
+    for item in PAUSED_ROUTES.iter(deps.storage)? {
+        if item?.1 {
+            paused_routes += 1;
+        }
+    }
+
+    to_binary(&ConfigResponse {
+        contract_version: env!("CARGO_PKG_VERSION").to_string(),
+        storage_version: VERSION.load(deps.storage)?,
+        admin: deps.api.addr_humanize(&state.admin)?,
+        rng_addr: deps.api.addr_humanize(&state.rng_addr)?,
+        rng_hash: state.rng_hash.clone(),
+        key_gen_status: state.key_gen_status,
+        keyed: state.keyed(),
+        key_epoch: state.key_epoch,
+        key_grace_period: state.key_grace_period,
+        permissionless: state.permissionless,
+        paused: state.paused,
+        paused_networks,
+        paused_routes,
+        task_timeout: state.task_timeout,
+        tx_cnt: state.tx_cnt,
+        outbound_cnt: state.outbound_cnt,
+        tasks_accepted: counters.accepted,
+        // counters that drifted apart must not make the query panic
+        tasks_pending: counters
+            .accepted
+            .saturating_sub(counters.completed)
+            .saturating_sub(counters.failed)
+            .saturating_sub(counters.expired),
+        tasks_completed: counters.completed,
+        tasks_failed: counters.failed,
+        tasks_expired: counters.expired,
+    })
+}
+
+fn query_routes(deps: Deps, page: Option<u32>, page_size: Option<u32>) -> StdResult<Binary> {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE) as usize;
+    let skip = page.unwrap_or(0) as usize * page_size;
+
+    // removed routes are kept in storage without handles
+    let mut routes = vec![];
+    let mut matched = 0;
+    for item in ROUTES.iter(deps.storage)? {
+        if routes.len() >= page_size {
+            break;
+        }
+        let (routing_info, route) = item?;
+        if route.handles.is_empty() {
+            continue;
+        }
+        matched += 1;
+        if matched <= skip {
+            continue;
+        }
+        let min_fee = ROUTE_FEES
+            .get(deps.storage, &routing_info)
+            .unwrap_or_default();
+        routes.push(RouteResponse {
+            routing_info,
+            code_hash: route.code_hash,
+            handles: route.handles,
+            min_fee,
+        });
+    }
+
+    to_binary(&RoutesResponse { routes })
+}
+
+fn query_signing_scheme(deps: Deps, network: String) -> StdResult<Binary> {
+    let scheme = SIGNING_SCHEMES
+        .get(deps.storage, &network)
+        .unwrap_or(SigningScheme::EvmPersonalSign);
+
+    to_binary(&SigningSchemeResponse { network, scheme })
+}
+
+fn query_pause_status(deps: Deps) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+
+    // unpaused entries are kept in storage with a `false` flag
+    let mut paused_networks = vec![];
+    for item in PAUSED_NETWORKS.iter(deps.storage)? {
+        let (source_network, paused) = item?;
+        if paused {
+            paused_networks.push(source_network);
+        }
+    }
+    let mut paused_routes = vec![];
+    for item in PAUSED_ROUTES.iter(deps.storage)? {
+        let (routing_info, paused) = item?;
+        if paused {
+            paused_routes.push(routing_info);
+        }
+    }
+
+    to_binary(&PauseStatusResponse {
+        paused: state.paused,
+        paused_networks,
+        paused_routes,
+    })
+}
+
+fn query_admin(deps: Deps) -> StdResult<Binary> {
+    let state: State = CONFIG.load(deps.storage)?;
+    let pending_admin = state
+        .pending_admin
+        .map(|pending_admin| deps.api.addr_humanize(&pending_admin))
+        .transpose()?;
+    let council = COUNCIL
+        .may_load(deps.storage)?
+        .map(|council| -> StdResult<CouncilResponse> {
+            Ok(CouncilResponse {
+                members: council
+                    .members
+                    .iter()
+                    .map(|member| deps.api.addr_humanize(member))
+                    .collect::<StdResult<Vec<Addr>>>()?,
+                threshold: council.threshold,
+            })
+        })
+        .transpose()?;
+
+    to_binary(&AdminResponse {
+        admin: deps.api.addr_humanize(&state.admin)?,
+        pending_admin,
+        council,
+    })
+}
+
+fn query_proposal(deps: Deps, proposal_id: u64) -> StdResult<Binary> {
+    let proposal = PROPOSALS
+        .get(deps.storage, &proposal_id)
+        .ok_or_else(|| StdError::generic_err("proposal not found"))?;
+
+    to_binary(&ProposalResponse {
+        action: proposal.action,
+        approvals: proposal
+            .approvals
+            .iter()
+            .map(|approval| deps.api.addr_humanize(approval))
+            .collect::<StdResult<Vec<Addr>>>()?,
+        executed: proposal.executed,
+    })
+}
+
+fn query_task_status(deps: Deps, source_network: String, task_id: u64) -> StdResult<Binary> {
+    let task_key: TaskKey = (source_network, task_id);
+
+    let response = if let Some(task_info) = TASK_MAP.get(deps.storage, &task_key) {
+        TaskStatusResponse {
+            status: TaskStatus::Pending,
+            updated_at: Some(task_info.accepted_height),
+            payload_hash: Some(task_info.payload_hash),
+            output_hash: None,
+        }
+    } else if let Some(closed_task) = CLOSED_TASKS.get(deps.storage, &task_key) {
+        TaskStatusResponse {
+            status: match closed_task.outcome {
+                TaskOutcome::Completed => TaskStatus::Completed,
+                TaskOutcome::Failed => TaskStatus::Failed,
+                TaskOutcome::Expired => TaskStatus::Expired,
+            },
+            updated_at: Some(closed_task.closed_at),
+            payload_hash: Some(closed_task.payload_hash),
+            output_hash: Some(closed_task.output_hash),
+        }
+    } else {
+        TaskStatusResponse {
+            status: TaskStatus::Unknown,
+            updated_at: None,
+            payload_hash: None,
+            output_hash: None,
+        }
+    };
+
+    to_binary(&response)
+}
+
+fn query_pending_tasks(
+    deps: Deps,
+    source_network: Option<String>,
+    routing_info: Option<Addr>,
+    page: Option<u32>,
+    page_size: Option<u32>,
+) -> StdResult<Binary> {
+    let page_size = page_size.unwrap_or(DEFAULT_PAGE_SIZE) as usize;
+    let skip = page.unwrap_or(0) as usize * page_size;
+
+    let mut tasks = vec![];
+    let mut matched = 0;
+    for item in TASK_MAP.iter(deps.storage)? {
+        if tasks.len() >= page_size {
+            break;
+        }
+        let (task_key, task_info) = item?;
+        if matches!(&source_network, Some(network) if *network != task_key.0)
+            || matches!(&routing_info, Some(route) if *route != task_info.routing_info)
+        {
+            continue;
+        }
+        matched += 1;
+        if matched <= skip {
+            continue;
+        }
+        tasks.push(PendingTask {
+            source_network: task_key.0,
+            task_id: task_key.1,
+            routing_info: task_info.routing_info,
+            accepted_height: task_info.accepted_height,
+            expires_at: task_info.expires_at,
+        });
+    }
+
+    to_binary(&PendingTasksResponse { tasks })
+}
+
+/////////////////////////////////////// Helpers /////////////////////////////////////
+
+/// Returns StdResult<()>
+///
+/// checks that the message sender is the gateway admin.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `info` - MessageInfo of the message being handled
+/// * `state` - the gateway's loaded configuration
+fn check_admin(deps: Deps, info: &MessageInfo, state: &State) -> StdResult<()> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if sender_raw != state.admin {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    Ok(())
+}
+
+/// Returns StdResult<Council>
+///
+/// loads the admin council, which only exists in multi-admin mode.
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+fn load_council(storage: &dyn Storage) -> StdResult<Council> {
+    COUNCIL
+        .may_load(storage)?
+        .ok_or_else(|| StdError::generic_err("no council has been set"))
+}
+
+/// Returns StdResult<CanonicalAddr>
+///
+/// checks that the message sender is a council member and returns its canonical address.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `info` - MessageInfo of the message being handled
+/// * `council` - the gateway's loaded council
+fn check_council_member(
+    deps: Deps,
+    info: &MessageInfo,
+    council: &Council,
+) -> StdResult<CanonicalAddr> {
+    let sender_raw = deps.api.addr_canonicalize(info.sender.as_str())?;
+    if !council.members.contains(&sender_raw) {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    Ok(sender_raw)
+}
+
+/// Returns StdResult<CosmosMsg>
+///
+/// creates the message asking scrt-rng for randomness that is later used to generate keys.
+///
+/// # Arguments
+///
+/// * `env` - Env of contract's environment
+/// * `entropy` - user supplied entropy for scrt-rng
+/// * `rng_hash` - code hash of the scrt-rng contract
+/// * `rng_addr` - address of the scrt-rng contract
+fn request_randomness(
+    env: Env,
+    entropy: String,
+    rng_hash: String,
+    rng_addr: Addr,
+) -> StdResult<CosmosMsg> {
+    SecretMsg::CreateRn {
+        cb_msg: Binary(vec![]),
+        entropy,
+        max_blk_delay: None,
+        purpose: Some(RNG_PURPOSE.to_string()),
+        receiver_addr: Some(env.contract.address),
+        receiver_code_hash: env.contract.code_hash,
+    }
+    .to_cosmos_msg(rng_hash, rng_addr.into_string(), None)
+}
+
+/// Task fields a result packet commits to.
+struct PacketTask<'a> {
+    /// Network the packet is sent to.
+    destination_network: &'a str,
+    /// The original, encrypted payload.
+    payload: &'a [u8],
+    /// The original payload_hash from the front-end.
+    payload_hash: &'a [u8],
+    /// A unique hash for the task.
+    input_hash: &'a [u8],
+    /// Key the result is encrypted with, `None` for plaintext results.
+    result_key: Option<&'a [u8]>,
+    /// Encryption format of the payload, also used for the result.
+    payload_version: u8,
+    /// Key epoch the task was accepted under.
+    key_epoch: u32,
+}
+
+impl<'a> From<&'a TaskInfo> for PacketTask<'a> {
+    fn from(task_info: &'a TaskInfo) -> Self {
+        // the original source network is now the routing destination
+        PacketTask {
+            destination_network: &task_info.source_network,
+            payload: task_info.payload.as_slice(),
+            payload_hash: task_info.payload_hash.as_slice(),
+            input_hash: &task_info.input_hash,
+            result_key: task_info.result_key.as_deref(),
+            payload_version: task_info.payload_version.unwrap_or(1),
+            key_epoch: task_info.key_epoch,
+        }
+    }
+}
+
+/// Returns StdResult<(Vec<Attribute>, Binary)>
+///
+/// hashes and signs a task result for the public network and returns the packet as plaintext log
+/// attributes, in the format relayers forward to the public gateway. Also returns the result hash
+/// included in the packet.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `task_id` - ID of the task on its source network
+/// * `task` - the task the result belongs to
+/// * `result` - JSON string of the task result
+fn signed_packet(
+    deps: Deps,
+    env: &Env,
+    task_id: u64,
+    task: PacketTask,
+    result: &str,
+) -> StdResult<(Vec<Attribute>, Binary)> {
+    // rename for clarity
+    let routing_info = task.destination_network;
+
+    // the packet commits to the encrypted result of tasks whose user asked for it
+    let result = match task.result_key {
+        Some(key) => {
+            let nonce_seed = [
+                routing_info.as_bytes(),
+                &task_id.to_le_bytes(),
+                task.input_hash,
+            ];
+            let version = PayloadVersion::new(task.payload_version)?;
+            encrypt_result(version, key, &nonce_seed.concat(), result)?
+        }
+        None => result.as_bytes().to_vec(),
+    };
+
+    // packets are hashed and signed the way the destination network verifies them
+    let scheme = SIGNING_SCHEMES
+        .get(deps.storage, &routing_info.to_string())
+        .unwrap_or(SigningScheme::EvmPersonalSign);
+
+    // tasks of a retired key epoch are signed with its key until the epoch expires, so public
+    // gateways that have not switched to the new verification key yet accept their results
+    let state = CONFIG.load(deps.storage)?;
+    let private_key = match KEY_HISTORY.get(deps.storage, &task.key_epoch) {
+        Some(retired) if epoch_decrypts_payloads(deps.storage, env, &state, task.key_epoch) => {
+            retired.signing_keys.sk
+        }
+        _ => state.signing_keys.sk,
+    };
+    let mut signing_key_bytes = [0u8; 32];
+    signing_key_bytes.copy_from_slice(private_key.as_slice());
+
+    // create message hash of (result + payload + inputs)
+    let data = SignedData::Result {
+        result: &result,
+        payload: task.payload,
+        input_hash: task.input_hash,
+    };
+    let result_signed = sign_with_scheme(deps, &scheme, &signing_key_bytes, &data)?;
+
+    // create hash of entire packet (used to verify the message wasn't modified in transit)
+    let data = SignedData::Packet {
+        destination_network: routing_info,
+        task_id,
+        payload: task.payload,
+        payload_hash: task.payload_hash,
+        result: &result,
+        result_hash: &result_signed.hash,
+        result_signature: &result_signed.signature,
+    };
+    let packet_signed = sign_with_scheme(deps, &scheme, &signing_key_bytes, &data)?;
+
+    // convert the hashes and signatures into hex byte strings
+    let payload_hash = format!("0x{}", task.payload_hash.encode_hex::<String>());
+    let result = format!("0x{}", result.encode_hex::<String>());
+    let result_signature = result_signed.signature_hex();
+    let output_hash = Binary(result_signed.digest);
+    let result_hash = format!("0x{}", output_hash.as_slice().encode_hex::<String>());
+    let packet_hash = format!("0x{}", packet_signed.digest.encode_hex::<String>());
+    let packet_signature = packet_signed.signature_hex();
+
+    let attributes = vec![
+        attr_plaintext("source_network", "secret"),
+        attr_plaintext("task_destination_network", routing_info),
+        attr_plaintext("task_id", task_id.to_string()),
+        attr_plaintext("payload_hash", payload_hash),
+        attr_plaintext("result", result),
+        attr_plaintext("result_hash", result_hash),
+        attr_plaintext("result_signature", result_signature),
+        attr_plaintext("packet_hash", packet_hash),
+        attr_plaintext("packet_signature", packet_signature),
+    ];
+
+    Ok((attributes, output_hash))
+}
+
+/// Returns StdResult<Vec<u8>>
+///
+/// encrypts a task result in the encryption format of its payload so only the user can read it.
+/// Returns the nonce followed by the ciphertext.
+///
+/// # Arguments
+///
+/// * `version` - encryption format of the payload
+/// * `key` - key shared with the user when the payload was decrypted
+/// * `nonce_seed` - bytes unique to the task, hashed into the nonce
+/// * `result` - JSON string of the task result
+fn encrypt_result(
+    version: PayloadVersion,
+    key: &[u8],
+    nonce_seed: &[u8],
+    result: &str,
+) -> StdResult<Vec<u8>> {
+    // every task of a user shares the key, so every task gets its own nonce
+    let nonce = &sha_256(nonce_seed)[..version.nonce_len()];
+    let ciphertext = version.encrypt(key, nonce, result.as_bytes(), &[])?;
+
+    Ok([nonce, &ciphertext].concat())
+}
+
+/// Parts of a packet the gateway signs.
+enum SignedData<'a> {
+    /// The task result and the inputs it was computed from.
+    Result {
+        /// JSON string of the task result.
+        result: &'a [u8],
+        /// The original, encrypted payload.
+        payload: &'a [u8],
+        /// A unique hash for the task.
+        input_hash: &'a [u8],
+    },
+    /// The entire packet, including the signed result.
+    Packet {
+        /// Network the packet is sent to.
+        destination_network: &'a str,
+        /// ID of the task on its source network.
+        task_id: u64,
+        /// The original, encrypted payload.
+        payload: &'a [u8],
+        /// The original payload_hash from the front-end.
+        payload_hash: &'a [u8],
+        /// JSON string of the task result.
+        result: &'a [u8],
+        /// Hash of the signed result.
+        result_hash: &'a [u8],
+        /// Result signature without a recovery byte.
+        result_signature: &'a [u8],
+    },
+}
+
+impl<'a> SignedData<'a> {
+    /// Returns the fields concatenated, as hashed by the schemes without typed data.
+    fn concat(&self) -> Vec<u8> {
+        match self {
+            SignedData::Result {
+                result,
+                payload,
+                input_hash,
+            } => [*result, payload, input_hash].concat(),
+            SignedData::Packet {
+                destination_network,
+                task_id,
+                payload,
+                payload_hash,
+                result,
+                result_hash,
+                result_signature,
+            } => [
+                "secret".as_bytes(),            // source network
+                destination_network.as_bytes(), // task_destination_network
+                &task_id.to_le_bytes(),         // task ID
+                payload,                        // payload (original encrypted payload)
+                payload_hash,                   // original payload message
+                result,                         // result
+                result_hash,                    // result message
+                result_signature,               // result signature
+            ]
+            .concat(),
+        }
+    }
+
+    /// Returns the EIP-712 hash of the fields as a `Result` or `Packet` struct.
+    fn eip712_struct_hash(&self) -> Vec<u8> {
+        // dynamic fields are encoded as their Keccak256 hash, the rest as 32 byte words
+        let encoded = match self {
+            SignedData::Result {
+                result,
+                payload,
+                input_hash,
+            } => [
+                &Keccak256::digest(EIP712_RESULT_TYPE.as_bytes())[..],
+                &Keccak256::digest(result),
+                &Keccak256::digest(payload),
+                input_hash,
+            ]
+            .concat(),
+            SignedData::Packet {
+                destination_network,
+                task_id,
+                payload,
+                payload_hash,
+                result,
+                result_hash,
+                result_signature,
+            } => [
+                &Keccak256::digest(EIP712_PACKET_TYPE.as_bytes())[..],
+                &Keccak256::digest("secret".as_bytes()),
+                &Keccak256::digest(destination_network.as_bytes()),
+                &uint256_word(*task_id),
+                &Keccak256::digest(payload),
+                &Keccak256::digest(payload_hash),
+                &Keccak256::digest(result),
+                result_hash,
+                &Keccak256::digest(result_signature),
+            ]
+            .concat(),
+        };
+
+        Keccak256::digest(encoded).to_vec()
+    }
+}
+
+/// Hash of packet data with its signature.
+struct SignedHash {
+    /// Hash of the data, chained into the packet hash.
+    hash: Vec<u8>,
+    /// Digest the signature was made over.
+    digest: Vec<u8>,
+    /// Signature without a recovery byte.
+    signature: Vec<u8>,
+    /// Recovery ID of secp256k1 signatures verified with `ecrecover`.
+    recovery_id: Option<u8>,
+}
+
+impl SignedHash {
+    /// Returns the signature as a '0x' prefixed hex string.
+    fn signature_hex(&self) -> String {
+        let signature = self.signature.encode_hex::<String>();
+        match self.recovery_id {
+            // `ecrecover` in Solidity expects the recovery ID offset by 27
+            Some(recovery_id) => format!("0x{}{:02x}", signature, recovery_id + 27),
+            None => format!("0x{}", signature),
+        }
+    }
+}
+
+/// Returns StdResult<SignedHash>
+///
+/// hashes and signs packet data in the format the destination network verifies.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `scheme` - signing scheme of the destination network
+/// * `signing_key` - the gateway's secp256k1 signing key
+/// * `data` - packet data to sign
+fn sign_with_scheme(
+    deps: Deps,
+    scheme: &SigningScheme,
+    signing_key: &[u8; 32],
+    data: &SignedData,
+) -> StdResult<SignedHash> {
+    let signed = match scheme {
+        SigningScheme::EvmPersonalSign | SigningScheme::Eip712 { .. } => {
+            let hash = match scheme {
+                SigningScheme::Eip712 {
+                    chain_id,
+                    verifying_contract,
+                } => eip712_hash(*chain_id, verifying_contract, &data.eip712_struct_hash())?,
+                _ => {
+                    // the first hash guarantees the message lenth is 32
+                    // the second hash prepends the Ethereum message, a requirement of `ecrecover`
+                    let message = Keccak256::digest(data.concat());
+                    Keccak256::digest([EVM_MESSAGE_PREFIX, &message].concat()).to_vec()
+                }
+            };
+
+            // `ecrecover` recovers the signer from the hash itself, so it is signed as is
+            let (signature, recovery_id) = secp256k1_sign_digest(&hash, signing_key)?;
+
+            SignedHash {
+                digest: hash.clone(),
+                signature,
+                recovery_id: Some(recovery_id),
+                hash,
+            }
+        }
+        SigningScheme::Secp256k1Sha256 => {
+            let data = data.concat();
+            let hash = sha_256(&data).to_vec();
+
+            // NOTE: api.secp256k1_sign() signs the sha_256 hash of the given data
+            SignedHash {
+                digest: hash.clone(),
+                signature: secp256k1_sign(deps, &data, signing_key)?,
+                recovery_id: None,
+                hash,
+            }
+        }
+        SigningScheme::Ed25519 => {
+            let hash = sha_256(&data.concat()).to_vec();
+            let signature = deps
+                .api
+                .ed25519_sign(&hash, &ed25519_signing_key(signing_key))
+                .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+            SignedHash {
+                digest: hash.clone(),
+                signature,
+                recovery_id: None,
+                hash,
+            }
+        }
+    };
+
+    Ok(signed)
+}
+
+/// Returns StdResult<Vec<u8>>
+///
+/// signs the SHA-256 hash of a message with the gateway's secp256k1 signing key, like
+/// `api.secp256k1_sign()`.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `message` - message to sign
+/// * `signing_key` - the gateway's secp256k1 signing key
+fn secp256k1_sign(deps: Deps, message: &[u8], signing_key: &[u8; 32]) -> StdResult<Vec<u8>> {
+    // used in production to create signatures
+    #[cfg(target_arch = "wasm32")]
+    let signature = deps
+        .api
+        .secp256k1_sign(message, signing_key)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    // used only in unit testing to create signatures
+    #[cfg(not(target_arch = "wasm32"))]
+    let signature = {
+        let _ = deps;
+        let secp = secp256k1::Secp256k1::signing_only();
+        let sk = secp256k1::SecretKey::from_slice(signing_key).unwrap();
+
+        let message = secp256k1::Message::from_slice(&sha_256(message))
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+        secp.sign_ecdsa(&message, &sk).serialize_compact().to_vec()
+    };
+
+    Ok(signature)
+}
+
+/// Returns StdResult<(Vec<u8>, u8)>
+///
+/// signs a 32 byte digest as is with the gateway's secp256k1 signing key and returns the signature
+/// with its recovery ID.
+/// NOTE: api.secp256k1_sign() cannot be used here, it signs the sha_256 hash of the given data
+///
+/// # Arguments
+///
+/// * `digest` - digest to sign
+/// * `signing_key` - the gateway's secp256k1 signing key
+fn secp256k1_sign_digest(digest: &[u8], signing_key: &[u8; 32]) -> StdResult<(Vec<u8>, u8)> {
+    let secp = secp256k1::Secp256k1::signing_only();
+    let sk = secp256k1::SecretKey::from_slice(signing_key)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let message = secp256k1::Message::from_slice(digest)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+
+    let (recovery_id, signature) = secp
+        .sign_ecdsa_recoverable(&message, &sk)
+        .serialize_compact();
+
+    Ok((signature.to_vec(), recovery_id.to_i32() as u8))
+}
+
+/// Returns StdResult<Vec<u8>>
+///
+/// hashes a struct as EIP-712 typed data in the domain of the public gateway.
+///
+/// # Arguments
+///
+/// * `chain_id` - chain ID of the destination network
+/// * `verifying_contract` - '0x' prefixed address of the public gateway
+/// * `struct_hash` - EIP-712 hash of the struct
+fn eip712_hash(chain_id: u64, verifying_contract: &str, struct_hash: &[u8]) -> StdResult<Vec<u8>> {
+    // addresses are encoded as left padded 32 byte words
+    let mut contract_word = [0u8; 32];
+    contract_word[12..].copy_from_slice(&evm_address(verifying_contract)?);
+
+    let domain_separator = Keccak256::digest(
+        [
+            &Keccak256::digest(EIP712_DOMAIN_TYPE.as_bytes())[..],
+            &Keccak256::digest(EIP712_NAME.as_bytes()),
+            &Keccak256::digest(EIP712_VERSION.as_bytes()),
+            &uint256_word(chain_id),
+            &contract_word,
+        ]
+        .concat(),
+    );
+
+    Ok(Keccak256::digest([&[0x19, 0x01][..], &domain_separator, struct_hash].concat()).to_vec())
+}
+
+/// Returns [u8; 32]
+///
+/// encodes an integer as a big endian 32 byte word, like Solidity's `uint256`.
+///
+/// # Arguments
+///
+/// * `value` - integer to encode
+fn uint256_word(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Returns StdResult<[u8; 20]>
+///
+/// parses a '0x' prefixed EVM address.
+///
+/// # Arguments
+///
+/// * `address` - address to parse
+fn evm_address(address: &str) -> StdResult<[u8; 20]> {
+    address
+        .strip_prefix("0x")
+        .and_then(|address| hex::decode(address).ok())
+        .and_then(|address| address.try_into().ok())
+        .ok_or_else(|| StdError::generic_err(format!("invalid EVM address {}", address)))
+}
+
+/// Returns [u8; 32]
+///
+/// derives the gateway's ed25519 signing key from its secp256k1 signing key, so both are rotated
+/// together.
+///
+/// # Arguments
+///
+/// * `signing_key` - the gateway's secp256k1 signing key
+fn ed25519_signing_key(signing_key: &[u8]) -> [u8; 32] {
+    sha_256(&[ED25519_KEY_PURPOSE.as_bytes(), signing_key].concat())
+}
+
+/// Returns StdResult<()>
+///
+/// applies an update to the gateway wide task counters.
+///
+/// # Arguments
+///
+/// * `storage` - mutable reference to the contract's storage
+/// * `update` - function updating the loaded counters
+fn update_task_counters(
+    storage: &mut dyn Storage,
+    update: impl FnOnce(&mut TaskCounters),
+) -> StdResult<()> {
+    let mut counters = TASK_COUNTERS.load(storage)?;
+    update(&mut counters);
+    TASK_COUNTERS.save(storage, &counters)
+}
+
+/// Returns Option<BankMsg>
+///
+/// returns the escrowed fee of a task that did not complete to the relayer that paid it.
+///
+/// # Arguments
+///
+/// * `task_info` - the task being closed
+fn refund_fee(task_info: &TaskInfo) -> Option<BankMsg> {
+    if task_info.fee.is_zero() {
+        return None;
+    }
+    Some(BankMsg::Send {
+        to_address: task_info.relayer.to_string(),
+        amount: coins(task_info.fee.u128(), FEE_DENOM),
+    })
+}
+
+/// Returns StdResult<String>
+///
+/// serializes the result sent to the public network for a task that failed.
+///
+/// # Arguments
+///
+/// * `code` - machine readable reason the task failed
+/// * `error` - human readable reason the task failed
+fn failure_result(code: FailureCode, error: &str) -> StdResult<String> {
+    serde_json_wasm::to_string(&FailureResult {
+        status: Failure,
+        code,
+        error: error.to_string(),
+    })
+    .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+/// Returns StdResult<()>
+///
+/// checks that a task has not been completed, expired or failed.
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+/// * `task_key` - source network and task ID of the task
+fn check_task_not_closed(storage: &dyn Storage, task_key: &TaskKey) -> StdResult<()> {
+    match CLOSED_TASKS.get(storage, task_key) {
+        Some(closed_task) => Err(StdError::generic_err(match closed_task.outcome {
+            TaskOutcome::Completed => "task has already been completed",
+            TaskOutcome::Expired => "task has expired",
+            TaskOutcome::Failed => "task has failed",
+        })),
+        None => Ok(()),
+    }
+}
+
+/// Returns u64
+///
+/// number of blocks a task sent to the given private contract can stay pending.
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+/// * `state` - the gateway's loaded configuration
+/// * `routing_info` - private contract the task is sent to
+fn task_timeout(storage: &dyn Storage, state: &State, routing_info: &Addr) -> u64 {
+    match ROUTE_TIMEOUTS.get(storage, routing_info) {
+        Some(blocks) if blocks > 0 => blocks,
+        _ => state.task_timeout,
+    }
+}
+
+/// Returns StdResult<(Payload, u32, Binary)>
+///
+/// decrypts the payload with the current encryption key, falling back to the previous key epoch
+/// while it is still within its grace period. Also returns the epoch and secret key of the key that
+/// was used.
+///
+/// # Arguments
+///
+/// * `deps` - reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `state` - the gateway's loaded configuration
+/// * `msg` - the relayed message containing the encrypted payload
+fn decrypt_with_key_epochs(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    msg: &PreExecutionMsg,
+) -> StdResult<(Payload, u32, Binary)> {
+    let err = match msg.decrypt_payload(state.encryption_keys.sk.clone()) {
+        Ok(payload) => return Ok((payload, state.key_epoch, state.encryption_keys.sk.clone())),
+        Err(err) => err,
+    };
+
+    let previous_epoch = match state.key_epoch.checked_sub(1) {
+        Some(epoch) => epoch,
+        None => return Err(err),
+    };
+    match KEY_HISTORY.get(deps.storage, &previous_epoch) {
+        Some(retired) if env.block.height < retired.expires_at => {
+            let payload = msg.decrypt_payload(retired.encryption_keys.sk.clone())?;
+            Ok((payload, previous_epoch, retired.encryption_keys.sk))
+        }
+        _ => Err(err),
+    }
+}
+
+/// Returns bool
+///
+/// whether payloads encrypted to a key epoch are still accepted, see `decrypt_with_key_epochs`.
+///
+/// # Arguments
+///
+/// * `storage` - reference to the contract's storage
+/// * `env` - Env of contract's environment
+/// * `state` - the gateway's loaded configuration
+/// * `epoch` - key epoch to check
+fn epoch_decrypts_payloads(storage: &dyn Storage, env: &Env, state: &State, epoch: u32) -> bool {
+    if epoch == state.key_epoch {
+        return true;
+    }
+    // only the previous epoch is tried, and only until it expires
+    if epoch + 1 != state.key_epoch {
+        return false;
+    }
+    matches!(
+        KEY_HISTORY.get(storage, &epoch),
+        Some(retired) if env.block.height < retired.expires_at
+    )
+}
+
+/// Returns (PublicKey, StaticSecret, Vec<u8>)
+///
+/// generates a public and privite key pair and generates a new PRNG_SEED with or without user entropy.
+///
+/// # Arguments
+///
+/// * `env` - contract's environment to be used for randomization
+/// * `prng_seed` - required prng seed for randomization
+/// * `user_entropy` - optional random string input by the user
+pub fn generate_keypair(
+    env: &Env,
+    prng_seed: Vec<u8>,
+    user_entropy: Option<String>,
+) -> Result<(PrivateKey, PublicKey, Vec<u8>), StdError> {
+    // generate new rng seed
+    let new_prng_bytes: [u8; 32] = match user_entropy {
+        Some(s) => new_entropy(env, prng_seed.as_ref(), s.as_bytes()),
+        None => new_entropy(env, prng_seed.as_ref(), prng_seed.as_ref()),
+    };
+
+    // generate and return key pair
+    let mut rng = Prng::new(prng_seed.as_ref(), new_prng_bytes.as_ref());
+    let sk = PrivateKey::parse(&rng.rand_bytes())?;
+    let pk = sk.pubkey();
+
+    Ok((sk, pk, new_prng_bytes.to_vec()))
+}
+
+/// Returns [u8;32]
+///
+/// generates new entropy from block data, does not save it to the contract.
+///
+/// # Arguments
+///
+/// * `env` - Env of contract's environment
+/// * `seed` - (user generated) seed for rng
+/// * `entropy` - Entropy seed saved in the contract
+pub fn new_entropy(env: &Env, seed: &[u8], entropy: &[u8]) -> [u8; 32] {
+    // 16 here represents the lengths in bytes of the block height and time.
+    let entropy_len = 16 + env.contract.address.to_string().len() + entropy.len();
+    let mut rng_entropy = Vec::with_capacity(entropy_len);
+    rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
+    rng_entropy.extend_from_slice(&env.block.time.seconds().to_be_bytes());
+    rng_entropy.extend_from_slice(env.contract.address.to_string().as_bytes());
+    rng_entropy.extend_from_slice(entropy);
+
+    let mut rng = Prng::new(seed, &rng_entropy);
+
+    rng.rand_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{evm_payload_hash, LegacyTaskRoute, ResponseStatus};
+    use crate::types::*;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_env, mock_info, MockApi, MockQuerier, MockStorage,
+    };
+    use cosmwasm_std::{from_binary, Addr, Api, Binary, Empty, OwnedDeps, ReplyOn};
+
+    use aes_gcm_siv::Aes256GcmSiv;
+    use chacha20poly1305::aead::{Aead, KeyInit};
+    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce, XChaCha20Poly1305, XNonce};
+    use hkdf::Hkdf;
+    use secp256k1::{ecdh::SharedSecret, Message, Secp256k1, SecretKey};
+    use sha2::Sha256;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const OWNER: &str = "admin0001";
+    const SOMEBODY: &str = "somebody";
+    const RNG_ADDR: &str = "address";
+    const ROUTING_INFO: &str = "secret19zpyd046u4swqpksr3n44cej4j8pg6ahw95y85";
+    const ROUTING_CODE_HASH: &str =
+        "2a2fbe493ef25b536bbe0baa3917b51e5ba092e14bd76abf50a59526e2789be3";
+    const INPUT_DATA: &str =
+        "{\"fingerprint\": \"0xF9BA143B95FF6D82\", \"location\": \"Menlo Park, CA\"}";
+
+    #[track_caller]
+    fn setup_test_case(deps: DepsMut) -> Result<Response<Empty>, StdError> {
+        // Instantiate a contract with entropy
+        let admin = Some(Addr::unchecked(OWNER.to_owned()));
+        let entropy = "secret".to_owned();
+        let rng_hash = "string".to_string();
+        let rng_addr = Addr::unchecked(RNG_ADDR.to_string());
+
+        let init_msg = InstantiateMsg {
+            admin,
+            entropy,
+            rng_hash,
+            rng_addr,
+            permissionless: None,
+            key_grace_period: None,
+            task_timeout: None,
+        };
+        instantiate(deps, mock_env(), mock_info(OWNER, &[]), init_msg)
+    }
+
+    #[track_caller]
+    fn generate_keys(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+        // admin asks scrt-rng to send back the randomness
+        let key_gen_msg = ExecuteMsg::KeyGen {};
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(OWNER, &[]),
+            key_gen_msg,
+        )
+        .unwrap();
+
+        // mock scrt-rng message
+        let mut rng = Prng::new(&[1, 2, 3], &[4, 5, 6]);
+        let fake_msg = ExecuteMsg::ReceiveFRn {
+            cb_msg: Binary(vec![]),
+            purpose: Some(RNG_PURPOSE.to_string()),
+            rn: rng.rand_bytes(),
+        };
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(RNG_ADDR, &[]),
+            fake_msg,
+        )
+        .unwrap();
+    }
+
+    #[track_caller]
+    fn register_relayer(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, relayer: &str) {
+        let add_msg = ExecuteMsg::AddRelayers {
+            relayers: vec![Addr::unchecked(relayer)],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), add_msg).unwrap();
+    }
+
+    /// Registers the private contract used by `mock_pre_execution_msg`.
+    #[track_caller]
+    fn register_route(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>) {
+        let route_msg = ExecuteMsg::SetRoute {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            code_hash: ROUTING_CODE_HASH.to_string(),
+            handles: vec!["test".to_string()],
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), route_msg).unwrap();
+    }
+
+    #[track_caller]
+    fn get_routes(deps: Deps) -> Vec<RouteResponse> {
+        let query_msg = QueryMsg::GetRoutes {
+            page: None,
+            page_size: None,
+        };
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: RoutesResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer.routes
+    }
+
+    #[track_caller]
+    fn get_relayer_status(deps: Deps, relayer: &str) -> RelayerStatus {
+        let query_msg = QueryMsg::GetRelayers {
+            page: None,
+            page_size: None,
+        };
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: RelayersResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer
+            .relayers
+            .into_iter()
+            .find(|status| status.address == relayer)
+            .unwrap()
+    }
+
+    #[track_caller]
+    fn get_admin(deps: Deps) -> AdminResponse {
+        let query_result = query(deps, mock_env(), QueryMsg::GetAdmin {});
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    #[track_caller]
+    fn get_proposal(deps: Deps, proposal_id: u64) -> ProposalResponse {
+        let query_result = query(deps, mock_env(), QueryMsg::GetProposal { proposal_id });
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    #[track_caller]
+    fn get_task_status(deps: Deps, task_id: u64) -> TaskStatusResponse {
+        let query_msg = QueryMsg::GetTaskStatus {
+            source_network: "ethereum".to_string(),
+            task_id,
+        };
+        let query_result = query(deps, mock_env(), query_msg);
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    #[track_caller]
+    fn get_pending_tasks(
+        deps: Deps,
+        source_network: Option<&str>,
+        routing_info: Option<&str>,
+        page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> Vec<(String, u64)> {
+        let query_msg = QueryMsg::GetPendingTasks {
+            source_network: source_network.map(|network| network.to_string()),
+            routing_info: routing_info.map(Addr::unchecked),
+            page,
+            page_size,
+        };
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: PendingTasksResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer
+            .tasks
+            .into_iter()
+            .map(|task| (task.source_network, task.task_id))
+            .collect()
+    }
+
+    #[track_caller]
+    fn get_config(deps: Deps) -> ConfigResponse {
+        let query_result = query(deps, mock_env(), QueryMsg::GetConfig {});
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    #[track_caller]
+    fn get_pause_status(deps: Deps) -> PauseStatusResponse {
+        let query_result = query(deps, mock_env(), QueryMsg::GetPauseStatus {});
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    #[track_caller]
+    fn get_gateway_encryption_key(deps: Deps) -> Binary {
+        let query_msg = QueryMsg::GetPublicKeys {};
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: PublicKeyResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer.encryption_key
+    }
+
+    #[track_caller]
+    fn get_public_keys(deps: Deps, epoch: Option<u32>) -> PublicKeyResponse {
+        let query_msg = match epoch {
+            Some(epoch) => QueryMsg::GetEpochPublicKeys { epoch },
+            None => QueryMsg::GetPublicKeys {},
+        };
+        let query_result = query(deps, mock_env(), query_msg);
+        from_binary(&query_result.unwrap()).unwrap()
+    }
+
+    /// Builds a valid input message with a payload encrypted to `gateway_pubkey`.
+    #[track_caller]
+    fn mock_pre_execution_msg(gateway_pubkey: &Binary, task_id: u64) -> PreExecutionMsg {
+        mock_pre_execution_msg_with_payload(gateway_pubkey, task_id, |_| {})
+    }
+
+    /// Builds a valid input message whose payload is changed by `customize` before encryption.
+    #[track_caller]
+    fn mock_pre_execution_msg_with_payload(
+        gateway_pubkey: &Binary,
+        task_id: u64,
+        customize: impl FnOnce(&mut Payload),
+    ) -> PreExecutionMsg {
+        // mock key pair
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(b"an example very very secret key.").unwrap();
+        let public_key = secp256k1::PublicKey::from_secret_key(&secp, &secret_key);
+
+        // create shared key from user private + gateway public
+        let gateway_pubkey = secp256k1::PublicKey::from_slice(gateway_pubkey.as_slice()).unwrap();
+        let shared_key = SharedSecret::new(&gateway_pubkey, &secret_key);
+
+        // mock Payload
+        let user_key = Binary(public_key.serialize().to_vec());
+        let mut payload = Payload {
+            data: INPUT_DATA.to_string(),
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            routing_code_hash: ROUTING_CODE_HASH.to_string(),
+            user_address: Addr::unchecked("some eth address"),
+            user_key: user_key.clone(),
+            gas_limit: None,
+            encrypt_result: false,
+        };
+        customize(&mut payload);
+        let serialized_payload = to_binary(&payload).unwrap();
+
+        // encrypt the payload
+        let cipher = ChaCha20Poly1305::new_from_slice(shared_key.as_ref()).unwrap();
+        // a fresh nonce for every message, as a client picking random nonces would
+        static NONCE_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nonce = format!("nonce {:06}", NONCE_COUNTER.fetch_add(1, Ordering::Relaxed));
+        let encrypted_payload = cipher
+            .encrypt(
+                Nonce::from_slice(nonce.as_bytes()), // 12-bytes; unique per message
+                serialized_payload.as_slice(),
+            )
+            .unwrap();
+
+        // sign the payload
+        let payload_hash = evm_payload_hash(&encrypted_payload);
+        let message = Message::from_slice(&payload_hash).unwrap();
+        let payload_signature = secp.sign_ecdsa(&message, &secret_key);
+
+        PreExecutionMsg {
+            task_id,
+            source_network: "ethereum".to_string(),
+            routing_info: payload.routing_info,
+            routing_code_hash: payload.routing_code_hash,
+            payload: Binary(encrypted_payload),
+            payload_hash: Binary(payload_hash.to_vec()),
+            payload_signature: Binary(payload_signature.serialize_compact().to_vec()),
+            user_address: payload.user_address,
+            user_key: user_key.clone(),
+            user_pubkey: user_key,
+            handle: "test".to_string(),
+            nonce: Binary(nonce.into_bytes()),
+            payload_version: None,
+        }
+    }
+
+    /// Sets the payload hash and signature of a mock message for its current payload.
+    fn sign_mock_payload(msg: &mut PreExecutionMsg) {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(b"an example very very secret key.").unwrap();
+        let payload_hash = evm_payload_hash(msg.payload.as_slice());
+        let message = Message::from_slice(&payload_hash).unwrap();
+        let payload_signature = secp.sign_ecdsa(&message, &secret_key);
+        msg.payload_hash = Binary(payload_hash.to_vec());
+        msg.payload_signature = Binary(payload_signature.serialize_compact().to_vec());
+    }
+
+    #[track_caller]
+    fn get_key_gen_status(deps: Deps) -> KeyGenStatus {
+        let query_msg = QueryMsg::GetKeyGenStatus {};
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: KeyGenStatusResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer.status
+    }
+
+    #[track_caller]
+    fn get_gateway_verification_key(deps: Deps) -> String {
+        let query_msg = QueryMsg::GetPublicKeys {};
+        let query_result = query(deps, mock_env(), query_msg);
+        let query_answer: PublicKeyResponse = from_binary(&query_result.unwrap()).unwrap();
+        query_answer.verification_key
+    }
+
+    #[test]
+    fn test_init() {
+        let mut deps = mock_dependencies();
+
+        let response = setup_test_case(deps.as_mut()).unwrap();
+        assert_eq!(1, response.messages.len());
+    }
+
+    #[test]
+    fn test_key_gen() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        assert_eq!(get_key_gen_status(deps.as_ref()), KeyGenStatus::Requested);
+
+        let receive_msg = ExecuteMsg::ReceiveFRn {
+            cb_msg: Binary(vec![]),
+            purpose: Some(RNG_PURPOSE.to_string()),
+            rn: [7u8; 32],
+        };
+
+        // scrt-rng callback before the randomness was fulfilled
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(RNG_ADDR, &[]),
+            receive_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("randomness has not been requested")
+        );
+
+        // only the admin can trigger key generation
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::KeyGen {},
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::KeyGen {},
+        )
+        .unwrap();
+        assert_eq!(get_key_gen_status(deps.as_ref()), KeyGenStatus::Fulfilled);
+
+        // randomness from anyone other than scrt-rng is rejected
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            receive_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("randomness must come from the configured scrt-rng contract")
+        );
+
+        // randomness requested for another purpose is rejected
+        let wrong_purpose_msg = ExecuteMsg::ReceiveFRn {
+            cb_msg: Binary(vec![]),
+            purpose: None,
+            rn: [7u8; 32],
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(RNG_ADDR, &[]),
+            wrong_purpose_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unexpected randomness purpose"));
+
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(RNG_ADDR, &[]),
+            receive_msg,
+        )
+        .unwrap();
+        assert_eq!(get_key_gen_status(deps.as_ref()), KeyGenStatus::Keyed);
+
+        // keys can only be created once
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info(OWNER, &[]),
+            ExecuteMsg::KeyGen {},
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("keys have already been created"));
+    }
+
+    #[test]
+    fn test_key_rotation() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, OWNER);
+        register_route(&mut deps);
+
+        let old_keys = get_public_keys(deps.as_ref(), None);
+        assert_eq!(old_keys.key_epoch, 0);
+        assert_eq!(old_keys.expires_at, None);
+
+        // only the admin can rotate keys
+        let rotate_msg = ExecuteMsg::RotateKeys {
+            entropy: "more entropy".to_string(),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            rotate_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            rotate_msg,
+        )
+        .unwrap();
+        assert_eq!(1, response.messages.len());
+        assert_eq!(get_key_gen_status(deps.as_ref()), KeyGenStatus::Requested);
+
+        // the old keys stay in use until the new randomness arrives
+        assert_eq!(get_public_keys(deps.as_ref(), None), old_keys);
+
+        generate_keys(&mut deps);
+        let new_keys = get_public_keys(deps.as_ref(), None);
+        assert_eq!(new_keys.key_epoch, 1);
+        assert_ne!(new_keys.encryption_key, old_keys.encryption_key);
+        assert_ne!(new_keys.verification_key, old_keys.verification_key);
+
+        // the retired verification key stays queryable
+        let retired_keys = get_public_keys(deps.as_ref(), Some(0));
+        assert_eq!(retired_keys.verification_key, old_keys.verification_key);
+        assert_eq!(
+            retired_keys.expires_at,
+            Some(env.block.height + DEFAULT_KEY_GRACE_PERIOD)
+        );
+
+        // payloads encrypted to the retired key are accepted during the grace period
+        for task_id in [1, 5] {
+            let handle_msg = ExecuteMsg::Input {
+                inputs: mock_pre_execution_msg(&old_keys.encryption_key, task_id),
+            };
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(OWNER, &[]),
+                handle_msg,
+            )
+            .unwrap();
+        }
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        assert_eq!(task_info.key_epoch, 0);
+
+        // returns the verification key recovered from the packet signature of a task's result
+        let decode = |value: &str| hex::decode(value.strip_prefix("0x").unwrap()).unwrap();
+        let post_result =
+            |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, env: Env, task_id: u64| {
+                let post_execution_msg = PostExecutionMsg {
+                    result: "{\"answer\": 42}".to_string(),
+                    source_network: "ethereum".to_string(),
+                    task_id,
+                    input_hash: Binary(
+                        sha_256(&[INPUT_DATA.as_bytes(), task_id.to_le_bytes().as_ref()].concat())
+                            .to_vec(),
+                    ),
+                };
+                let logs = execute(
+                    deps.as_mut(),
+                    env,
+                    mock_info(ROUTING_INFO, &[]),
+                    ExecuteMsg::Output {
+                        outputs: post_execution_msg,
+                    },
+                )
+                .unwrap()
+                .attributes;
+                let signature = decode(&logs[8].value);
+                deps.as_ref()
+                    .api
+                    .secp256k1_recover_pubkey(
+                        &decode(&logs[7].value),
+                        &signature[..64],
+                        signature[64] - 27,
+                    )
+                    .unwrap()
+            };
+
+        // results of tasks from the retired epoch are signed with its key during the grace period
+        let recovered = post_result(&mut deps, env.clone(), 1);
+        assert_eq!(recovered, decode(&old_keys.verification_key));
+
+        // and rejected once it has passed
+        let mut late_env = mock_env();
+        late_env.block.height += DEFAULT_KEY_GRACE_PERIOD;
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&old_keys.encryption_key, 2),
+        };
+        let err = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(OWNER, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("aead::Error"));
+
+        // and their results are signed with the current key once it has passed
+        let recovered = post_result(&mut deps, late_env, 5);
+        assert_eq!(recovered, decode(&new_keys.verification_key));
+
+        // revoking the retired epoch rejects it immediately
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::RevokeKeyEpoch { epoch: 0 },
+        )
+        .unwrap();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&old_keys.encryption_key, 3),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("aead::Error"));
+        let retired_keys = get_public_keys(deps.as_ref(), Some(0));
+        assert_eq!(retired_keys.expires_at, Some(env.block.height));
+
+        // payloads encrypted to the new key are accepted
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&new_keys.encryption_key, 4),
+        };
+        execute(deps.as_mut(), env, mock_info(OWNER, &[]), handle_msg).unwrap();
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 4))
+            .unwrap();
+        assert_eq!(task_info.key_epoch, 1);
+    }
+
+    #[test]
+    fn test_task_keys() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        let pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, 1);
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), handle_msg.clone()).unwrap();
+
+        // a pending task cannot be resubmitted
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(err, StdError::generic_err("task is already pending"));
+
+        // the same task ID from another network is a different task
+        let mut other_network_msg = mock_pre_execution_msg(&gateway_pubkey, 1);
+        other_network_msg.source_network = "polygon".to_string();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: other_network_msg,
+        };
+        execute(deps.as_mut(), env, info, handle_msg).unwrap();
+
+        let ethereum_task = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        let polygon_task = TASK_MAP
+            .get(deps.as_ref().storage, &("polygon".to_string(), 1))
+            .unwrap();
+        assert_eq!(ethereum_task.source_network, "ethereum");
+        assert_eq!(polygon_task.source_network, "polygon");
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // only the admin can pause
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::All,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), pause_msg.clone()).unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), pause_msg).unwrap();
+        assert!(get_pause_status(deps.as_ref()).paused);
+
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err =
+            execute(deps.as_mut(), env.clone(), info.clone(), handle_msg.clone()).unwrap_err();
+        assert_eq!(err, StdError::generic_err("gateway is paused"));
+
+        let unpause_msg = ExecuteMsg::Unpause {
+            scope: PauseScope::All,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            unpause_msg,
+        )
+        .unwrap();
+        execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+
+        // pausing a source network only affects tasks coming from it
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::Network {
+                network: "ethereum".to_string(),
+            },
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), pause_msg).unwrap();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 2),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("source network ethereum is paused")
+        );
+
+        let mut other_network_msg = mock_pre_execution_msg(&gateway_pubkey, 2);
+        other_network_msg.source_network = "polygon".to_string();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: other_network_msg,
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+
+        // pausing a route rejects every task sent to it
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::Route {
+                routing_info: Addr::unchecked(ROUTING_INFO),
+            },
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), pause_msg).unwrap();
+        let mut other_network_msg = mock_pre_execution_msg(&gateway_pubkey, 3);
+        other_network_msg.source_network = "polygon".to_string();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: other_network_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("route {} is paused", ROUTING_INFO))
+        );
+
+        // another spelling of the paused route does not get around it
+        let mut other_network_msg = mock_pre_execution_msg(&gateway_pubkey, 4);
+        other_network_msg.source_network = "polygon".to_string();
+        other_network_msg.routing_info = Addr::unchecked(ROUTING_INFO.to_uppercase());
+        let handle_msg = ExecuteMsg::Input {
+            inputs: other_network_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info, handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("Invalid input: address not normalized")
+        );
+
+        let status = get_pause_status(deps.as_ref());
+        assert!(!status.paused);
+        assert_eq!(status.paused_networks, vec!["ethereum".to_string()]);
+        assert_eq!(status.paused_routes, vec![Addr::unchecked(ROUTING_INFO)]);
+
+        // tasks already in flight can still complete
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        execute(deps.as_mut(), env, mock_info(ROUTING_INFO, &[]), handle_msg).unwrap();
+    }
+
+    #[test]
+    fn test_admin_transfer() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+
+        // only the admin can nominate a new admin
+        let propose_msg = ExecuteMsg::ProposeAdmin {
+            new_admin: Addr::unchecked(SOMEBODY),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            propose_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            propose_msg,
+        )
+        .unwrap();
+
+        // the admin does not change until the handover is accepted
+        let admin = get_admin(deps.as_ref());
+        assert_eq!(admin.admin, Addr::unchecked(OWNER));
+        assert_eq!(admin.pending_admin, Some(Addr::unchecked(SOMEBODY)));
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("sender is not the pending admin")
+        );
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::AcceptAdmin {},
+        )
+        .unwrap();
+
+        let admin = get_admin(deps.as_ref());
+        assert_eq!(admin.admin, Addr::unchecked(SOMEBODY));
+        assert_eq!(admin.pending_admin, None);
+
+        // the previous admin lost its rights
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::All,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            pause_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(deps.as_mut(), env, mock_info(SOMEBODY, &[]), pause_msg).unwrap();
+    }
+
+    #[test]
+    fn test_council() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let members = vec![
+            Addr::unchecked("member1"),
+            Addr::unchecked("member2"),
+            Addr::unchecked("member3"),
+        ];
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+
+        // the threshold must be reachable
+        let council_msg = ExecuteMsg::SetCouncil {
+            members: members.clone(),
+            threshold: 4,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            council_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("threshold must be between 1 and the number of council members")
+        );
+
+        let council_msg = ExecuteMsg::SetCouncil {
+            members: members.clone(),
+            threshold: 2,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            council_msg,
+        )
+        .unwrap();
+        let council = get_admin(deps.as_ref()).council.unwrap();
+        assert_eq!(council.members, members);
+        assert_eq!(council.threshold, 2);
+
+        // sensitive actions can no longer be sent directly by the admin
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::Pause {
+                scope: PauseScope::All,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("action requires council approval")
+        );
+        for msg in [
+            ExecuteMsg::SetKeyGracePeriod { blocks: 10 },
+            ExecuteMsg::RevokeKeyEpoch { epoch: 0 },
+            ExecuteMsg::SetTaskTimeout { blocks: 10 },
+            ExecuteMsg::SetRouteTimeout {
+                routing_info: Addr::unchecked(ROUTING_INFO),
+                blocks: 10,
+            },
+            ExecuteMsg::SetRouteFee {
+                routing_info: Addr::unchecked(ROUTING_INFO),
+                min_fee: Uint128::new(10),
+            },
+        ] {
+            let err = execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), msg).unwrap_err();
+            assert_eq!(
+                err,
+                StdError::generic_err("action requires council approval")
+            );
+        }
+
+        // only members can propose
+        let propose_msg = ExecuteMsg::Propose {
+            action: AdminAction::Pause {
+                scope: PauseScope::All,
+            },
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            propose_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member1", &[]),
+            propose_msg,
+        )
+        .unwrap();
+
+        // a single approval is not enough
+        let proposal = get_proposal(deps.as_ref(), 0);
+        assert_eq!(proposal.approvals, vec![Addr::unchecked("member1")]);
+        assert!(!proposal.executed);
+        assert!(!get_pause_status(deps.as_ref()).paused);
+
+        let approve_msg = ExecuteMsg::Approve { proposal_id: 0 };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member1", &[]),
+            approve_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("proposal has already been approved")
+        );
+
+        // the second approval executes the action
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member2", &[]),
+            approve_msg.clone(),
+        )
+        .unwrap();
+        assert!(get_proposal(deps.as_ref(), 0).executed);
+        assert!(get_pause_status(deps.as_ref()).paused);
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member3", &[]),
+            approve_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("proposal has already been executed")
+        );
+
+        // a pending proposal and one disbanding the council
+        let propose_msg = ExecuteMsg::Propose {
+            action: AdminAction::Unpause {
+                scope: PauseScope::All,
+            },
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member1", &[]),
+            propose_msg,
+        )
+        .unwrap();
+        let propose_msg = ExecuteMsg::Propose {
+            action: AdminAction::SetCouncil {
+                members: vec![],
+                threshold: 0,
+            },
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member1", &[]),
+            propose_msg,
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("member3", &[]),
+            ExecuteMsg::Approve { proposal_id: 2 },
+        )
+        .unwrap();
+        assert_eq!(get_admin(deps.as_ref()).council, None);
+
+        // the admin is back in charge
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::Unpause {
+                scope: PauseScope::All,
+            },
+        )
+        .unwrap();
+        assert!(!get_pause_status(deps.as_ref()).paused);
+
+        // proposals made to a previous council cannot be approved
+        let council_msg = ExecuteMsg::SetCouncil {
+            members,
+            threshold: 2,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            council_msg,
+        )
+        .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("member2", &[]),
+            ExecuteMsg::Approve { proposal_id: 1 },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("proposal was made to a previous council")
+        );
+    }
+
+    #[test]
+    fn test_expire_tasks() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // only the admin can change timeouts
+        let timeout_msg = ExecuteMsg::SetRouteTimeout {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            blocks: 100,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            info.clone(),
+            timeout_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            timeout_msg,
+        )
+        .unwrap();
+
+        // tasks record when they were accepted and when they time out
+        for task_id in 1..=2 {
+            let handle_msg = ExecuteMsg::Input {
+                inputs: mock_pre_execution_msg(&gateway_pubkey, task_id),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+        }
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        assert_eq!(task_info.accepted_height, env.block.height);
+        assert_eq!(task_info.accepted_time, env.block.time.seconds());
+        assert_eq!(task_info.expires_at, env.block.height + 100);
+
+        // nothing expires before the timeout
+        let expire_msg = ExecuteMsg::ExpireTasks { limit: Some(1) };
+        let response =
+            execute(deps.as_mut(), env.clone(), info.clone(), expire_msg.clone()).unwrap();
+        assert!(response.events.is_empty());
+
+        // anyone can expire timed out tasks, up to the limit
+        let mut late_env = mock_env();
+        late_env.block.height += 100;
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            info.clone(),
+            expire_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(response.events.len(), 1);
+
+        // the timeout packet uses the same attributes as `Output`
+        let logs = &response.events[0].attributes;
+        assert_eq!(response.events[0].ty, "task_timeout");
+        assert_eq!(logs[0].value, "secret".to_string());
+        assert_eq!(logs[1].value, "ethereum".to_string());
+        assert_eq!(logs[2].value, "1".to_string());
+        let result = hex::decode(logs[4].value.strip_prefix("0x").unwrap()).unwrap();
+        let result: FailureResult = serde_json_wasm::from_slice(&result).unwrap();
+        assert_eq!(result.status, Failure);
+        assert_eq!(result.code, FailureCode::Timeout);
+        assert_eq!(result.error, TIMEOUT_ERROR);
+        assert_eq!(
+            hex::decode(logs[8].value.strip_prefix("0x").unwrap())
+                .unwrap()
+                .len(),
+            65
+        );
+
+        // the next sweep picks up the remaining task
+        let response = execute(deps.as_mut(), late_env.clone(), info.clone(), expire_msg).unwrap();
+        assert_eq!(response.events.len(), 1);
+        assert_eq!(response.events[0].attributes[2].value, "2".to_string());
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            info.clone(),
+            ExecuteMsg::ExpireTasks { limit: None },
+        )
+        .unwrap();
+        assert!(response.events.is_empty());
+
+        // expired tasks are removed with their reply entries
+        assert_eq!(TASK_MAP.get_len(&deps.storage).unwrap(), 0);
+        for task_id in 1..=2 {
+            assert!(!TASK_MAP.contains(&deps.storage, &("ethereum".to_string(), task_id)));
+        }
+        assert_eq!(REPLY_TASKS.get_len(&deps.storage).unwrap(), 0);
+        assert!(!REPLY_TASKS.contains(&deps.storage, &task_info.reply_id.unwrap()));
+
+        // expired tasks can no longer complete or be resubmitted
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let err = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("task has expired"));
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err = execute(deps.as_mut(), late_env, info, handle_msg).unwrap_err();
+        assert_eq!(err, StdError::generic_err("task has expired"));
+    }
+
+    #[test]
+    fn test_private_call_failure() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // the private contract is called through a submessage that only replies on error
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let response = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].reply_on, ReplyOn::Error);
+        assert_eq!(response.messages[0].gas_limit, None);
+        let first_reply_id = response.messages[0].id;
+
+        // the task keeps its reply ID so the reply entry can be removed when it closes
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        assert_eq!(task_info.reply_id, Some(first_reply_id));
+
+        // the payload can limit the gas of the private contract call
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg_with_payload(&gateway_pubkey, 2, |payload| {
+                payload.gas_limit = Some(150_000)
+            }),
+        };
+        let response = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+        assert_eq!(response.messages[0].gas_limit, Some(150_000));
+        assert_ne!(response.messages[0].id, first_reply_id);
+
+        // replies for unknown calls are rejected
+        let err = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 42,
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unknown reply ID 42"));
+
+        // a failed call closes the task with a signed failure packet
+        let response = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: first_reply_id,
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap();
+        let response_data: InputResponse = from_binary(&response.data.unwrap()).unwrap();
+        assert_eq!(response_data.status, Failure);
+        let logs = &response.attributes;
+        assert_eq!(logs[0].value, "secret".to_string());
+        assert_eq!(logs[1].value, "ethereum".to_string());
+        assert_eq!(logs[2].value, "1".to_string());
+        let result = hex::decode(logs[4].value.strip_prefix("0x").unwrap()).unwrap();
+        let result: FailureResult = serde_json_wasm::from_slice(&result).unwrap();
+        assert_eq!(result.status, Failure);
+        assert_eq!(result.code, FailureCode::ExecutionFailed);
+        assert_eq!(result.error, EXECUTION_ERROR);
+        assert_eq!(
+            hex::decode(logs[8].value.strip_prefix("0x").unwrap())
+                .unwrap()
+                .len(),
+            65
+        );
+
+        // the failed task and its reply entry are removed, the other task is still pending
+        assert!(!TASK_MAP.contains(&deps.storage, &("ethereum".to_string(), 1)));
+        assert!(TASK_MAP.contains(&deps.storage, &("ethereum".to_string(), 2)));
+        assert!(!REPLY_TASKS.contains(&deps.storage, &first_reply_id));
+        let err = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: first_reply_id,
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("unknown reply ID {}", first_reply_id))
+        );
+
+        // failed tasks can no longer complete or be resubmitted
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("task has failed"));
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err = execute(deps.as_mut(), env, info, handle_msg).unwrap_err();
+        assert_eq!(err, StdError::generic_err("task has failed"));
+    }
+
+    #[test]
+    fn test_task_status() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // tasks the gateway has never seen are unknown
+        let status = get_task_status(deps.as_ref(), 1);
+        assert_eq!(status.status, TaskStatus::Unknown);
+        assert_eq!(status.updated_at, None);
+        assert_eq!(status.payload_hash, None);
+
+        // accepted tasks are pending at the private contract
+        let mut reply_ids = vec![];
+        for task_id in 1..=3 {
+            let pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, task_id);
+            let payload_hash = pre_execution_msg.payload_hash.clone();
+            let handle_msg = ExecuteMsg::Input {
+                inputs: pre_execution_msg,
+            };
+            let response = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+            reply_ids.push(response.messages[0].id);
+
+            let status = get_task_status(deps.as_ref(), task_id);
+            assert_eq!(status.status, TaskStatus::Pending);
+            assert_eq!(status.updated_at, Some(env.block.height));
+            assert_eq!(status.payload_hash, Some(payload_hash));
+            assert_eq!(status.output_hash, None);
+        }
+
+        // the status of a task does not depend on other networks using the same ID
+        let query_msg = QueryMsg::GetTaskStatus {
+            source_network: "polygon".to_string(),
+            task_id: 1,
+        };
+        let status: TaskStatusResponse =
+            from_binary(&query(deps.as_ref(), env.clone(), query_msg).unwrap()).unwrap();
+        assert_eq!(status.status, TaskStatus::Unknown);
+
+        // completed tasks report the hash of the posted result
+        let mut late_env = mock_env();
+        late_env.block.height += 10;
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let result_hash = &response.attributes[5].value;
+        let status = get_task_status(deps.as_ref(), 1);
+        assert_eq!(status.status, TaskStatus::Completed);
+        assert_eq!(status.updated_at, Some(late_env.block.height));
+        assert_eq!(
+            status.output_hash,
+            Some(Binary(
+                hex::decode(result_hash.strip_prefix("0x").unwrap()).unwrap()
+            ))
+        );
+
+        // failed private contract calls
+        reply(
+            deps.as_mut(),
+            late_env.clone(),
+            Reply {
+                id: reply_ids[1],
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap();
+        let status = get_task_status(deps.as_ref(), 2);
+        assert_eq!(status.status, TaskStatus::Failed);
+        assert_eq!(status.updated_at, Some(late_env.block.height));
+
+        // timed out tasks
+        late_env.block.height += DEFAULT_TASK_TIMEOUT;
+        execute(
+            deps.as_mut(),
+            late_env.clone(),
+            info,
+            ExecuteMsg::ExpireTasks { limit: None },
+        )
+        .unwrap();
+        let status = get_task_status(deps.as_ref(), 3);
+        assert_eq!(status.status, TaskStatus::Expired);
+        assert_eq!(status.updated_at, Some(late_env.block.height));
+        assert!(status.output_hash.is_some());
+    }
+
+    #[test]
+    fn test_pending_tasks() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+        assert!(get_pending_tasks(deps.as_ref(), None, None, None, None).is_empty());
+
+        // two tasks from ethereum and one from polygon
+        for (source_network, task_id) in [("ethereum", 1), ("polygon", 1), ("ethereum", 2)] {
+            let mut pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, task_id);
+            pre_execution_msg.source_network = source_network.to_string();
+            let handle_msg = ExecuteMsg::Input {
+                inputs: pre_execution_msg,
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+        }
+
+        let ethereum_1 = ("ethereum".to_string(), 1);
+        let polygon_1 = ("polygon".to_string(), 1);
+        let ethereum_2 = ("ethereum".to_string(), 2);
+        assert_eq!(
+            get_pending_tasks(deps.as_ref(), None, None, None, None),
+            vec![ethereum_1.clone(), polygon_1.clone(), ethereum_2.clone()]
+        );
+
+        // filter by source network and route
+        assert_eq!(
+            get_pending_tasks(deps.as_ref(), Some("ethereum"), None, None, None),
+            vec![ethereum_1.clone(), ethereum_2.clone()]
+        );
+        assert_eq!(
+            get_pending_tasks(
+                deps.as_ref(),
+                Some("polygon"),
+                Some(ROUTING_INFO),
+                None,
+                None
+            ),
+            vec![polygon_1.clone()]
+        );
+        assert!(
+            get_pending_tasks(deps.as_ref(), None, Some("another contract"), None, None).is_empty()
+        );
+
+        // pages are counted after filtering
+        assert_eq!(
+            get_pending_tasks(deps.as_ref(), Some("ethereum"), None, Some(1), Some(1)),
+            vec![ethereum_2.clone()]
+        );
+        assert!(
+            get_pending_tasks(deps.as_ref(), Some("ethereum"), None, Some(2), Some(1)).is_empty()
+        );
+
+        // completed tasks are no longer listed
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        // the last pending task takes the place of the removed one
+        assert_eq!(
+            get_pending_tasks(deps.as_ref(), None, None, None, None),
+            vec![ethereum_2, polygon_1]
+        );
+        assert!(!TASK_MAP.contains(&deps.storage, &ethereum_1));
+    }
+
+    #[test]
+    fn test_routes() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(SOMEBODY, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+        assert!(get_routes(deps.as_ref()).is_empty());
+
+        // tasks to unregistered private contracts are rejected
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("route {} is not registered", ROUTING_INFO))
+        );
+
+        // only the admin can register routes
+        let route_msg = ExecuteMsg::SetRoute {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            code_hash: ROUTING_CODE_HASH.to_string(),
+            handles: vec!["test".to_string(), "another".to_string()],
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), route_msg.clone()).unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        let empty_route_msg = ExecuteMsg::SetRoute {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            code_hash: ROUTING_CODE_HASH.to_string(),
+            handles: vec![],
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            empty_route_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("a route needs at least one handle")
+        );
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), route_msg).unwrap();
+        assert_eq!(
+            get_routes(deps.as_ref()),
+            vec![RouteResponse {
+                routing_info: Addr::unchecked(ROUTING_INFO),
+                code_hash: ROUTING_CODE_HASH.to_string(),
+                handles: vec!["test".to_string(), "another".to_string()],
+                min_fee: Uint128::zero(),
+            }]
+        );
+
+        // the code hash is pinned
+        let mut pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, 1);
+        pre_execution_msg.routing_code_hash = "0".repeat(64);
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("routing code hash does not match the registered route")
+        );
+
+        // and so are the handles
+        let mut pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, 1);
+        pre_execution_msg.handle = "withdraw".to_string();
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!(
+                "handle withdraw is not allowed for route {}",
+                ROUTING_INFO
+            ))
+        );
+
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+
+        // removed routes stop accepting tasks
+        let remove_msg = ExecuteMsg::RemoveRoute {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            remove_msg.clone(),
+        )
+        .unwrap();
+        assert!(get_routes(deps.as_ref()).is_empty());
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            remove_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("route {} is not registered", ROUTING_INFO))
+        );
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 2),
+        };
+        let err = execute(deps.as_mut(), env, info, handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(format!("route {} is not registered", ROUTING_INFO))
+        );
+    }
+
+    #[test]
+    fn test_fees() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // only the admin can set route fees
+        let fee_msg = ExecuteMsg::SetRouteFee {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            min_fee: Uint128::new(100),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            fee_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), fee_msg).unwrap();
+        assert_eq!(get_routes(deps.as_ref())[0].min_fee, Uint128::new(100));
+
+        // tasks have to pay at least the route minimum in uscrt
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &coins(99, FEE_DENOM)),
+            handle_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("task fee of 99uscrt is below the route minimum of 100uscrt")
+        );
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &coins(100, "uatom")),
+            handle_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("only uscrt is accepted as a task fee")
+        );
+
+        // the fee is held in escrow with the task
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &coins(100, FEE_DENOM)),
+            handle_msg,
+        )
+        .unwrap();
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        assert_eq!(task_info.fee, Uint128::new(100));
+        let mut reply_ids = vec![];
+        for (task_id, fee) in [(2, 150), (3, 120)] {
+            let handle_msg = ExecuteMsg::Input {
+                inputs: mock_pre_execution_msg(&gateway_pubkey, task_id),
+            };
+            let response = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SOMEBODY, &coins(fee, FEE_DENOM)),
+                handle_msg,
+            )
+            .unwrap();
+            reply_ids.push(response.messages[0].id);
+        }
+        assert_eq!(
+            get_relayer_status(deps.as_ref(), SOMEBODY).claimable_fees,
+            Uint128::zero()
+        );
+
+        // completed tasks credit the relayer
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        assert!(response.messages.is_empty());
+        assert_eq!(
+            get_relayer_status(deps.as_ref(), SOMEBODY).claimable_fees,
+            Uint128::new(100)
+        );
+
+        // failed and expired tasks are refunded
+        let refund = |amount: u128| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: SOMEBODY.to_string(),
+                amount: coins(amount, FEE_DENOM),
+            })
+        };
+        let response = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: reply_ids[1],
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap();
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].msg, refund(120));
+
+        let mut late_env = mock_env();
+        late_env.block.height += DEFAULT_TASK_TIMEOUT;
+        let response = execute(
+            deps.as_mut(),
+            late_env,
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::ExpireTasks { limit: None },
+        )
+        .unwrap();
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].msg, refund(150));
+
+        // relayers claim their earned fees
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::ClaimFees {},
+        )
+        .unwrap();
+        assert_eq!(response.messages[0].msg, refund(100));
+        assert_eq!(
+            get_relayer_status(deps.as_ref(), SOMEBODY).claimable_fees,
+            Uint128::zero()
+        );
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::ClaimFees {},
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("no fees to claim"));
+    }
+
+    #[test]
+    fn test_input_batch() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+        let fee_msg = ExecuteMsg::SetRouteFee {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            min_fee: Uint128::new(10),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), fee_msg).unwrap();
+
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::InputBatch { inputs: vec![] },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("batch has no inputs"));
+
+        // rejected tasks do not sink the rest of the batch
+        let mut wrong_handle = mock_pre_execution_msg(&gateway_pubkey, 2);
+        wrong_handle.handle = "withdraw".to_string();
+        let handle_msg = ExecuteMsg::InputBatch {
+            inputs: vec![
+                mock_pre_execution_msg(&gateway_pubkey, 1),
+                mock_pre_execution_msg(&gateway_pubkey, 1),
+                wrong_handle,
+                mock_pre_execution_msg(&gateway_pubkey, 3),
+            ],
+        };
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &coins(25, FEE_DENOM)),
+            handle_msg,
+        )
+        .unwrap();
+        let batch: InputBatchResponse = from_binary(&response.data.unwrap()).unwrap();
+        let statuses: Vec<(u64, ResponseStatus, Option<String>)> = batch
+            .results
+            .into_iter()
+            .map(|result| (result.task_id, result.status, result.error))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (1, Success, None),
+                (
+                    1,
+                    Failure,
+                    Some(StdError::generic_err("task is already pending").to_string())
+                ),
+                (
+                    2,
+                    Failure,
+                    Some(
+                        StdError::generic_err(format!(
+                            "handle withdraw is not allowed for route {}",
+                            ROUTING_INFO
+                        ))
+                        .to_string()
+                    )
+                ),
+                (3, Success, None),
+            ]
+        );
+
+        // accepted tasks are dispatched and pay the route minimum, the rest is returned
+        assert_eq!(response.messages.len(), 3);
+        assert_eq!(response.messages[0].reply_on, ReplyOn::Error);
+        assert_eq!(response.messages[1].reply_on, ReplyOn::Error);
+        assert_eq!(
+            response.messages[2].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: SOMEBODY.to_string(),
+                amount: coins(5, FEE_DENOM),
+            })
+        );
+        for task_id in [1, 3] {
+            let task_info = TASK_MAP
+                .get(deps.as_ref().storage, &("ethereum".to_string(), task_id))
+                .unwrap();
+            assert_eq!(task_info.fee, Uint128::new(10));
+        }
+        assert!(!TASK_MAP.contains(deps.as_ref().storage, &("ethereum".to_string(), 2)));
+
+        // tasks the attached funds no longer cover are rejected
+        let handle_msg = ExecuteMsg::InputBatch {
+            inputs: vec![
+                mock_pre_execution_msg(&gateway_pubkey, 4),
+                mock_pre_execution_msg(&gateway_pubkey, 5),
+            ],
+        };
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &coins(15, FEE_DENOM)),
+            handle_msg,
+        )
+        .unwrap();
+        let batch: InputBatchResponse = from_binary(&response.data.unwrap()).unwrap();
+        assert_eq!(batch.results[0].status, Success);
+        assert_eq!(
+            batch.results[1].error,
+            Some(
+                StdError::generic_err("task fee of 5uscrt is below the route minimum of 10uscrt")
+                    .to_string()
+            )
+        );
+        let reply_id = response.messages[0].id;
+
+        // a failed call keeps the batch results as the response data
+        let response = reply(
+            deps.as_mut(),
+            env,
+            Reply {
+                id: reply_id,
+                result: SubMsgResult::Err("out of gas".to_string()),
+            },
+        )
+        .unwrap();
+        assert_eq!(response.data, None);
+        assert_eq!(response.attributes[2].value, "4".to_string());
+    }
+
+    #[test]
+    fn test_outbound() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_route(&mut deps);
+        let outbound_msg = ExecuteMsg::Outbound {
+            destination_network: "ethereum".to_string(),
+            result: "{\"price\": 42}".to_string(),
+        };
+
+        // only registered private contracts can send outbound messages
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            outbound_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("sender is not a registered private contract")
+        );
+
+        // outbound messages are numbered by the gateway apart from tasks and signed like `Output`
+        for outbound_id in 1..=2 {
+            let response = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(ROUTING_INFO, &[]),
+                outbound_msg.clone(),
+            )
+            .unwrap();
+            let logs = &response.attributes;
+            assert_eq!(logs[0].value, "secret".to_string());
+            assert_eq!(logs[1].value, "ethereum".to_string());
+            assert_eq!(logs[2].value, outbound_id.to_string());
+            assert_eq!(
+                logs[3].value,
+                format!(
+                    "0x{}",
+                    sha_256(ROUTING_INFO.as_bytes()).encode_hex::<String>()
+                )
+            );
+            assert_eq!(
+                logs[4].value,
+                format!("0x{}", "{\"price\": 42}".encode_hex::<String>())
+            );
+            assert_eq!(
+                hex::decode(logs[8].value.strip_prefix("0x").unwrap())
+                    .unwrap()
+                    .len(),
+                65
+            );
+            assert_eq!(logs[9].value, "outbound".to_string());
+            assert_eq!(logs[10].value, ROUTING_INFO.to_string());
+        }
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.tx_cnt, 0);
+        assert_eq!(config.outbound_cnt, 2);
+
+        // paused destination networks do not receive outbound messages
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::Network {
+                network: "ethereum".to_string(),
+            },
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), pause_msg).unwrap();
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info(ROUTING_INFO, &[]),
+            outbound_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("destination network ethereum is paused")
+        );
+    }
+
+    #[test]
+    fn test_signing_schemes() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_route(&mut deps);
+
+        // networks without a scheme are signed for EVM personal-sign
+        let query_msg = QueryMsg::GetSigningScheme {
+            network: "cosmoshub".to_string(),
+        };
+        let query_answer: SigningSchemeResponse =
+            from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+        assert_eq!(query_answer.scheme, SigningScheme::EvmPersonalSign);
+
+        // only the admin can set signing schemes
+        let scheme_msg = |network: &str, scheme: SigningScheme| ExecuteMsg::SetSigningScheme {
+            network: network.to_string(),
+            scheme,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            scheme_msg("cosmoshub", SigningScheme::Secp256k1Sha256),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+
+        // EIP-712 domains need a valid public gateway address
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            scheme_msg(
+                "sepolia",
+                SigningScheme::Eip712 {
+                    chain_id: 11155111,
+                    verifying_contract: "0x1234".to_string(),
+                },
+            ),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("invalid EVM address 0x1234"));
+
+        for (network, scheme) in [
+            ("cosmoshub", SigningScheme::Secp256k1Sha256),
+            ("solana", SigningScheme::Ed25519),
+            (
+                "sepolia",
+                SigningScheme::Eip712 {
+                    chain_id: 11155111,
+                    verifying_contract: "0x2C1d60e34727a773799F9820C06b6fda2FEfcA7B".to_string(),
+                },
+            ),
+        ] {
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(OWNER, &[]),
+                scheme_msg(network, scheme),
+            )
+            .unwrap();
+        }
+        let public_keys = get_public_keys(deps.as_ref(), None);
+        let decode = |value: &str| hex::decode(value.strip_prefix("0x").unwrap()).unwrap();
+        let outbound = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, network: &str| {
+            let outbound_msg = ExecuteMsg::Outbound {
+                destination_network: network.to_string(),
+                result: "{\"score\": 700}".to_string(),
+            };
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(ROUTING_INFO, &[]),
+                outbound_msg,
+            )
+            .unwrap()
+            .attributes
+        };
+
+        // Cosmos chains verify SHA-256 hashes with the gateway's secp256k1 key
+        let logs = outbound(&mut deps, "cosmoshub");
+        let input_hash = sha_256(&["{\"score\": 700}".as_bytes(), &1u64.to_le_bytes()].concat());
+        let result_hash = sha_256(
+            &[
+                "{\"score\": 700}".as_bytes(),
+                ROUTING_INFO.as_bytes(),
+                &input_hash,
+            ]
+            .concat(),
+        );
+        assert_eq!(decode(&logs[5].value), result_hash.to_vec());
+        let verification_key = decode(&public_keys.verification_key);
+        for (hash, signature) in [(5, 6), (7, 8)] {
+            assert_eq!(decode(&logs[signature].value).len(), 64);
+            assert!(deps
+                .as_ref()
+                .api
+                .secp256k1_verify(
+                    &decode(&logs[hash].value),
+                    &decode(&logs[signature].value),
+                    &verification_key
+                )
+                .unwrap());
+        }
+
+        // Solana and NEAR style chains verify SHA-256 hashes with the gateway's ed25519 key
+        let logs = outbound(&mut deps, "solana");
+        let verification_key = decode(&public_keys.ed25519_verification_key.unwrap());
+        for (hash, signature) in [(5, 6), (7, 8)] {
+            assert!(deps
+                .as_ref()
+                .api
+                .ed25519_verify(
+                    &decode(&logs[hash].value),
+                    &decode(&logs[signature].value),
+                    &verification_key
+                )
+                .unwrap());
+        }
+
+        // EVM chains `ecrecover` the gateway's key from the hashes and signatures as emitted
+        let evm_logs = outbound(&mut deps, "ethereum");
+        let eip712_logs = outbound(&mut deps, "sepolia");
+        let verification_key = decode(&public_keys.verification_key);
+        for logs in [&evm_logs, &eip712_logs] {
+            for (hash, signature) in [(5, 6), (7, 8)] {
+                let signature = decode(&logs[signature].value);
+                assert_eq!(signature.len(), 65);
+                assert!(signature[64] == 27 || signature[64] == 28);
+                let recovered = deps
+                    .as_ref()
+                    .api
+                    .secp256k1_recover_pubkey(
+                        &decode(&logs[hash].value),
+                        &signature[..64],
+                        signature[64] - 27,
+                    )
+                    .unwrap();
+                assert_eq!(recovered, verification_key);
+            }
+        }
+
+        // EVM personal-sign results are signed behind the Ethereum message prefix
+        let result = "{\"score\": 700}".as_bytes();
+        let input_hash = sha_256(&[result, &3u64.to_le_bytes()].concat());
+        let message = Keccak256::digest([result, ROUTING_INFO.as_bytes(), &input_hash].concat());
+        let prefixed =
+            Keccak256::digest(["\x19Ethereum Signed Message:\n32".as_bytes(), &message].concat());
+        assert_eq!(decode(&evm_logs[5].value), prefixed.to_vec());
+
+        // EIP-712 results are signed as `Result` structs in the domain of the public gateway.
+        // the expected hashes were computed independently of this contract, by encoding the
+        // structs by hand from the EIP-712 rules and hashing them with a reference Keccak-256
+        let input_hash = sha_256(&[result, &4u64.to_le_bytes()].concat());
+        let result_data = SignedData::Result {
+            result,
+            payload: ROUTING_INFO.as_bytes(),
+            input_hash: &input_hash,
+        };
+        let typed_hash =
+            hex::decode("c8d5559d67726a9f4715d751f477472c6f8c33f0e3b3dcc54613ab21331dcf47")
+                .unwrap();
+        assert_eq!(decode(&eip712_logs[5].value), typed_hash);
+        // the same struct signed for another chain has a different hash
+        assert_eq!(
+            eip712_hash(
+                1,
+                "0x2C1d60e34727a773799F9820C06b6fda2FEfcA7B",
+                &result_data.eip712_struct_hash()
+            )
+            .unwrap(),
+            hex::decode("f29df6b85f4b5dc2d4045de517edce28fefa7792e574d1cfab33d7693782f361")
+                .unwrap()
+        );
+
+        // packets are signed as `Packet` structs, checked with a fixed result signature
+        let payload_hash = sha_256(ROUTING_INFO.as_bytes());
+        let result_signature: Vec<u8> = (0..64).collect();
+        let packet_data = SignedData::Packet {
+            destination_network: "sepolia",
+            task_id: 4,
+            payload: ROUTING_INFO.as_bytes(),
+            payload_hash: &payload_hash,
+            result,
+            result_hash: &typed_hash,
+            result_signature: &result_signature,
+        };
+        assert_eq!(
+            eip712_hash(
+                11155111,
+                "0x2C1d60e34727a773799F9820C06b6fda2FEfcA7B",
+                &packet_data.eip712_struct_hash()
+            )
+            .unwrap(),
+            hex::decode("370a14a03a8bdc95f0108059d64fb3ba25adb5d7a401b0409cd5d2747b2e6d54")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encrypted_result() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // the user asks for the result to be encrypted to their key
+        let pre_execution_msg =
+            mock_pre_execution_msg_with_payload(&gateway_pubkey, 1, |payload| {
+                payload.encrypt_result = true
+            });
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg,
+        )
+        .unwrap();
+
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let logs = execute(deps.as_mut(), env, mock_info(ROUTING_INFO, &[]), handle_msg)
+            .unwrap()
+            .attributes;
+
+        // the packet carries the nonce and ciphertext in place of the result
+        let encrypted = hex::decode(logs[4].value.strip_prefix("0x").unwrap()).unwrap();
+        assert_ne!(encrypted, "{\"answer\": 42}".as_bytes());
+
+        // the user decrypts it with the key they encrypted the payload with
+        let secret_key = SecretKey::from_slice(b"an example very very secret key.").unwrap();
+        let gateway_pubkey = secp256k1::PublicKey::from_slice(gateway_pubkey.as_slice()).unwrap();
+        let shared_key = SharedSecret::new(&gateway_pubkey, &secret_key);
+        let cipher = ChaCha20Poly1305::new_from_slice(shared_key.as_ref()).unwrap();
+        let (nonce, ciphertext) = encrypted.split_at(12);
+        let result = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .unwrap();
+        assert_eq!(result, "{\"answer\": 42}".as_bytes());
+    }
+
+    #[test]
+    fn test_payload_versions() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // keys the user shares with the gateway
+        let secret_key = SecretKey::from_slice(b"an example very very secret key.").unwrap();
+        let shared_key = SharedSecret::new(
+            &secp256k1::PublicKey::from_slice(gateway_pubkey.as_slice()).unwrap(),
+            &secret_key,
+        );
+        let mut hkdf_key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, shared_key.as_ref())
+            .expand(b"TNLS payload encryption", &mut hkdf_key)
+            .unwrap();
+
+        // re-encrypts the mock payload in the format of another version
+        let versioned_msg =
+            |task_id: u64, version: u8, nonce: &[u8], ciphertext: &dyn Fn(&[u8]) -> Vec<u8>| {
+                let mut msg = mock_pre_execution_msg(&gateway_pubkey, task_id);
+                let plaintext = ChaCha20Poly1305::new(Key::from_slice(shared_key.as_ref()))
+                    .decrypt(
+                        Nonce::from_slice(msg.nonce.as_slice()),
+                        msg.payload.as_slice(),
+                    )
+                    .unwrap();
+                msg.payload = Binary(ciphertext(&plaintext));
+                msg.nonce = Binary(nonce.to_vec());
+                msg.payload_version = Some(version);
+                sign_mock_payload(&mut msg);
+                msg
+            };
+        let chacha_msg = versioned_msg(1, 2, b"chacha nonce", &|plaintext| {
+            ChaCha20Poly1305::new(Key::from_slice(&hkdf_key))
+                .encrypt(Nonce::from_slice(b"chacha nonce"), plaintext)
+                .unwrap()
+        });
+        let xchacha_msg = versioned_msg(2, 3, b"a 24 byte unique nonce!!", &|plaintext| {
+            XChaCha20Poly1305::new(Key::from_slice(&hkdf_key))
+                .encrypt(XNonce::from_slice(b"a 24 byte unique nonce!!"), plaintext)
+                .unwrap()
+        });
+        let aes_msg = versioned_msg(3, 4, b"aesgcm nonce", &|plaintext| {
+            Aes256GcmSiv::new_from_slice(&hkdf_key)
+                .unwrap()
+                .encrypt(aes_gcm_siv::Nonce::from_slice(b"aesgcm nonce"), plaintext)
+                .unwrap()
+        });
+
+        // every version is accepted, and a missing version is the original format
+        for msg in [
+            chacha_msg,
+            xchacha_msg.clone(),
+            aes_msg,
+            mock_pre_execution_msg(&gateway_pubkey, 4),
+        ] {
+            let handle_msg = ExecuteMsg::Input { inputs: msg };
+            execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SOMEBODY, &[]),
+                handle_msg,
+            )
+            .unwrap();
+        }
+
+        // unknown versions are rejected
+        let mut pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, 5);
+        pre_execution_msg.payload_version = Some(9);
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unsupported payload version 9"));
+
+        // the nonce must fit the cipher of the version
+        let mut pre_execution_msg = xchacha_msg;
+        pre_execution_msg.task_id = 5;
+        pre_execution_msg.nonce = Binary(b"unique nonce".to_vec());
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env, mock_info(SOMEBODY, &[]), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("payload version 3 needs a 24 byte nonce")
+        );
+    }
+
+    #[test]
+    fn test_payload_associated_data() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        // the user binds the payload to the task metadata
+        let secret_key = SecretKey::from_slice(b"an example very very secret key.").unwrap();
+        let shared_key = SharedSecret::new(
+            &secp256k1::PublicKey::from_slice(gateway_pubkey.as_slice()).unwrap(),
+            &secret_key,
+        );
+        let mut hkdf_key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, shared_key.as_ref())
+            .expand(b"TNLS payload encryption", &mut hkdf_key)
+            .unwrap();
+        let bound_msg = |task_id: u64| {
+            let mut msg = mock_pre_execution_msg(&gateway_pubkey, task_id);
+            let plaintext = ChaCha20Poly1305::new(Key::from_slice(shared_key.as_ref()))
+                .decrypt(
+                    Nonce::from_slice(msg.nonce.as_slice()),
+                    msg.payload.as_slice(),
+                )
+                .unwrap();
+            let aad = [
+                &4u32.to_be_bytes()[..],
+                b"test",
+                &64u32.to_be_bytes(),
+                ROUTING_CODE_HASH.as_bytes(),
+                &8u32.to_be_bytes(),
+                b"ethereum",
+                &16u32.to_be_bytes(),
+                b"some eth address",
+            ]
+            .concat();
+            assert_eq!(msg.associated_data(), aad);
+            let payload = chacha20poly1305::aead::Payload {
+                msg: &plaintext,
+                aad: &aad,
+            };
+            msg.payload = Binary(
+                ChaCha20Poly1305::new(Key::from_slice(&hkdf_key))
+                    .encrypt(Nonce::from_slice(msg.nonce.as_slice()), payload)
+                    .unwrap(),
+            );
+            msg.payload_version = Some(5);
+            sign_mock_payload(&mut msg);
+            msg
+        };
+
+        // the payload decrypts with the metadata it was bound to
+        let handle_msg = ExecuteMsg::Input {
+            inputs: bound_msg(1),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg,
+        )
+        .unwrap();
+
+        // a relayer cannot pair it with other metadata, even metadata the route checks let through:
+        // the route also allows another handle and code hashes are compared case insensitively
+        let route_msg = ExecuteMsg::SetRoute {
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            code_hash: ROUTING_CODE_HASH.to_string(),
+            handles: vec!["test".to_string(), "other".to_string()],
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), route_msg).unwrap();
+        let tampered: [&dyn Fn(&mut PreExecutionMsg); 4] = [
+            &|msg| msg.handle = "other".to_string(),
+            &|msg| msg.routing_code_hash = ROUTING_CODE_HASH.to_uppercase(),
+            &|msg| msg.source_network = "other".to_string(),
+            &|msg| msg.user_address = Addr::unchecked("other"),
+        ];
+        for tamper in tampered {
+            let mut pre_execution_msg = bound_msg(2);
+            tamper(&mut pre_execution_msg);
+            let handle_msg = ExecuteMsg::Input {
+                inputs: pre_execution_msg,
+            };
+            let err = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info(SOMEBODY, &[]),
+                handle_msg,
+            )
+            .unwrap_err();
+            assert_eq!(err, StdError::generic_err("aead::Error"));
+        }
+
+        // the same ciphertext without the version binding the metadata does not decrypt either
+        let mut pre_execution_msg = bound_msg(2);
+        pre_execution_msg.payload_version = Some(2);
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env, mock_info(SOMEBODY, &[]), handle_msg).unwrap_err();
+        assert_eq!(err, StdError::generic_err("aead::Error"));
+    }
+
+    #[test]
+    fn test_nonce_reuse() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, OWNER);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+
+        let pre_execution_msg = mock_pre_execution_msg(&gateway_pubkey, 1);
+        let nonce_key = sha_256(
+            &[
+                pre_execution_msg.user_key.as_slice(),
+                pre_execution_msg.nonce.as_slice(),
+            ]
+            .concat(),
+        );
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg.clone(),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            handle_msg,
+        )
+        .unwrap();
+
+        // the captured payload cannot be replayed under another task ID
+        let mut replayed_msg = pre_execution_msg;
+        replayed_msg.task_id = 2;
+        let handle_msg = ExecuteMsg::Input {
+            inputs: replayed_msg,
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("nonce has already been used with this user key")
+        );
+
+        // a payload with a fresh nonce is accepted
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 2),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            handle_msg,
+        )
+        .unwrap();
+
+        // nonces are kept while their key epoch still decrypts payloads
+        let prune_msg = ExecuteMsg::PruneNonces { limit: None };
+        let response = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            prune_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(response.attributes[0].value, "0");
+
+        // and pruned once the retired epoch expires
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            ExecuteMsg::RotateKeys {
+                entropy: "more entropy".to_string(),
+            },
+        )
+        .unwrap();
+        generate_keys(&mut deps);
+        let response = execute(
+            deps.as_mut(),
+            env,
+            mock_info(SOMEBODY, &[]),
+            prune_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(response.attributes[0].value, "0");
+
+        // the retired epoch still decrypts payloads up to the block before it expires
+        let expires_at = get_public_keys(deps.as_ref(), Some(0)).expires_at.unwrap();
+        let mut late_env = mock_env();
+        late_env.block.height = expires_at - 1;
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(SOMEBODY, &[]),
+            prune_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(response.attributes[0].value, "0");
+
+        // from then on its nonces are pruned, a limited sweep leaves the rest for the next one
+        late_env.block.height = expires_at;
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(SOMEBODY, &[]),
+            ExecuteMsg::PruneNonces { limit: Some(1) },
+        )
+        .unwrap();
+        assert_eq!(response.attributes[0].value, "1");
+        assert_eq!(SEEN_NONCES.get_len(&deps.storage).unwrap(), 1);
+        let response = execute(
+            deps.as_mut(),
+            late_env.clone(),
+            mock_info(SOMEBODY, &[]),
+            prune_msg.clone(),
+        )
+        .unwrap();
+        assert_eq!(response.attributes[0].value, "1");
+        assert_eq!(SEEN_NONCES.get_len(&deps.storage).unwrap(), 0);
+        assert!(!SEEN_NONCES.contains(&deps.storage, &nonce_key));
+
+        // removed nonces are gone, nothing is left to prune
+        let response =
+            execute(deps.as_mut(), late_env, mock_info(SOMEBODY, &[]), prune_msg).unwrap();
+        assert_eq!(response.attributes[0].value, "0");
+    }
+
+    #[test]
+    fn test_relayer_registry() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
 
-    rng.rand_bytes()
-}
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_route(&mut deps);
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-    use cosmwasm_std::{from_binary, Addr, Binary, Empty};
+        // unregistered relayers are rejected
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 1),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("sender is not a registered relayer")
+        );
 
-    use chacha20poly1305::aead::{Aead, NewAead};
-    use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
-    use secp256k1::{ecdh::SharedSecret, Message, Secp256k1, SecretKey};
+        // only the admin can edit the registry
+        let add_msg = ExecuteMsg::AddRelayers {
+            relayers: vec![Addr::unchecked(SOMEBODY)],
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            add_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::generic_err("unauthorized"));
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), add_msg).unwrap();
 
-    const OWNER: &str = "admin0001";
-    const SOMEBODY: &str = "somebody";
+        // registered relayers can submit tasks
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let status = get_relayer_status(deps.as_ref(), SOMEBODY);
+        assert!(status.registered);
+        assert_eq!(status.tasks_submitted, 1);
+        assert_eq!(status.tasks_completed, 0);
 
-    #[track_caller]
-    fn setup_test_case(deps: DepsMut) -> Result<Response<Empty>, StdError> {
-        // Instantiate a contract with entropy
-        let admin = Some(Addr::unchecked(OWNER.to_owned()));
-        let entropy = "secret".to_owned();
-        let rng_hash = "string".to_string();
-        let rng_addr = Addr::unchecked("address".to_string());
+        // completed tasks are credited to the submitting relayer
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let status = get_relayer_status(deps.as_ref(), SOMEBODY);
+        assert_eq!(status.tasks_completed, 1);
 
-        let init_msg = InstantiateMsg {
-            admin,
-            entropy,
-            rng_hash,
-            rng_addr,
+        // removed relayers keep their counters but can no longer submit
+        let remove_msg = ExecuteMsg::RemoveRelayers {
+            relayers: vec![Addr::unchecked(SOMEBODY)],
         };
-        instantiate(deps, mock_env(), mock_info(OWNER, &[]), init_msg)
-    }
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            remove_msg,
+        )
+        .unwrap();
+        let status = get_relayer_status(deps.as_ref(), SOMEBODY);
+        assert!(!status.registered);
+        assert_eq!(status.tasks_submitted, 1);
 
-    #[track_caller]
-    fn get_gateway_encryption_key(deps: Deps) -> Binary {
-        let query_msg = QueryMsg::GetPublicKeys {};
-        let query_result = query(deps, mock_env(), query_msg);
-        let query_answer: PublicKeyResponse = from_binary(&query_result.unwrap()).unwrap();
-        let gateway_pubkey = query_answer.encryption_key;
-        gateway_pubkey
-    }
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&gateway_pubkey, 2),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg.clone(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("sender is not a registered relayer")
+        );
 
-    #[track_caller]
-    fn get_gateway_verification_key(deps: Deps) -> String {
-        let query_msg = QueryMsg::GetPublicKeys {};
-        let query_result = query(deps, mock_env(), query_msg);
-        let query_answer: PublicKeyResponse = from_binary(&query_result.unwrap()).unwrap();
-        let gateway_pubkey = query_answer.verification_key;
-        gateway_pubkey
+        // permissionless mode accepts anyone
+        let permissionless_msg = ExecuteMsg::SetPermissionless {
+            permissionless: true,
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(OWNER, &[]),
+            permissionless_msg,
+        )
+        .unwrap();
+        execute(deps.as_mut(), env, mock_info(SOMEBODY, &[]), handle_msg).unwrap();
+        let status = get_relayer_status(deps.as_ref(), SOMEBODY);
+        assert_eq!(status.tasks_submitted, 2);
     }
 
     #[test]
-    fn test_init() {
+    fn test_migrate() {
         let mut deps = mock_dependencies();
+        let env = mock_env();
 
-        let response = setup_test_case(deps.as_mut()).unwrap();
-        assert_eq!(1, response.messages.len());
+        // keys and a pending task written by the original layout
+        let (secret, public, prng_seed) = generate_keypair(&env, vec![1u8; 32], None).unwrap();
+        let encryption_keys = KeyPair {
+            sk: Binary(secret.serialize().to_vec()),
+            pk: Binary(public.serialize_compressed().to_vec()),
+        };
+        let (secret, public, _) = generate_keypair(&env, prng_seed, None).unwrap();
+        let signing_keys = KeyPair {
+            sk: Binary(secret.serialize().to_vec()),
+            pk: Binary(public.serialize().to_vec()),
+        };
+        let legacy_state = v0::State {
+            admin: deps.api.addr_canonicalize(OWNER).unwrap(),
+            keyed: true,
+            tx_cnt: 7,
+            encryption_keys: encryption_keys.clone(),
+            signing_keys: signing_keys.clone(),
+        };
+        v0::CONFIG
+            .save(deps.as_mut().storage, &legacy_state)
+            .unwrap();
+        let legacy_task = v0::TaskInfo {
+            payload: Binary(b"payload".to_vec()),
+            payload_hash: Binary(b"payload hash".to_vec()),
+            input_hash: [1u8; 32],
+            source_network: "ethereum".to_string(),
+            user_address: Addr::unchecked("some eth address"),
+        };
+        v0::TASK_MAP
+            .insert(deps.as_mut().storage, &1, &legacy_task)
+            .unwrap();
+
+        // the scrt-rng contract and the routes of pending tasks must be supplied
+        let mut migrate_msg = MigrateMsg {
+            rng_addr: None,
+            rng_hash: None,
+            permissionless: None,
+            key_grace_period: None,
+            task_timeout: None,
+            task_routes: vec![],
+        };
+        let err = migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err(
+                "rng_addr and rng_hash are required to migrate from storage version 0"
+            )
+        );
+
+        migrate_msg.rng_addr = Some(Addr::unchecked(RNG_ADDR));
+        migrate_msg.rng_hash = Some("string".to_string());
+        let err = migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("missing route for pending task 1")
+        );
+
+        migrate_msg.task_routes = vec![LegacyTaskRoute {
+            task_id: 1,
+            routing_info: Addr::unchecked(ROUTING_INFO),
+            routing_code_hash: ROUTING_CODE_HASH.to_string(),
+        }];
+        migrate(deps.as_mut(), env.clone(), migrate_msg.clone()).unwrap();
+        assert_eq!(
+            VERSION.load(deps.as_ref().storage).unwrap(),
+            CURRENT_VERSION
+        );
+
+        // the keys survive the upgrade
+        let state = CONFIG.load(deps.as_ref().storage).unwrap();
+        assert_eq!(state.encryption_keys, encryption_keys);
+        assert_eq!(state.signing_keys, signing_keys);
+        assert_eq!(state.key_gen_status, KeyGenStatus::Keyed);
+        assert_eq!(state.tx_cnt, 7);
+        assert!(state.permissionless);
+
+        // and so does the pending task
+        let task_info = TASK_MAP
+            .get(deps.as_ref().storage, &("ethereum".to_string(), 1))
+            .unwrap();
+        assert_eq!(task_info.input_hash, legacy_task.input_hash);
+        assert_eq!(task_info.routing_info, Addr::unchecked(ROUTING_INFO));
+        assert_eq!(task_info.relayer, Addr::unchecked(OWNER));
+        assert_eq!(task_info.accepted_height, env.block.height);
+        assert_eq!(
+            task_info.expires_at,
+            env.block.height + DEFAULT_TASK_TIMEOUT
+        );
+
+        // payloads encrypted to the original key are still accepted
+        register_route(&mut deps);
+        let handle_msg = ExecuteMsg::Input {
+            inputs: mock_pre_execution_msg(&encryption_keys.pk, 2),
+        };
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info(SOMEBODY, &[]),
+            handle_msg,
+        )
+        .unwrap();
+
+        // counting starts with the migrated task
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.storage_version, CURRENT_VERSION);
+        assert_eq!(config.tasks_accepted, 2);
+        assert_eq!(config.tasks_pending, 2);
+
+        // migrating an up to date contract changes nothing
+        migrate(deps.as_mut(), env, migrate_msg).unwrap();
+        assert_eq!(
+            CONFIG.load(deps.as_ref().storage).unwrap().encryption_keys,
+            encryption_keys
+        );
     }
 
     #[test]
     fn test_query() {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let info = mock_info(OWNER, &[]);
 
         // initialize
         setup_test_case(deps.as_mut()).unwrap();
-
-        // mock scrt-rng message
-        let mut rng = Prng::new(&[1, 2, 3], &[4, 5, 6]);
-        let fake_msg = ExecuteMsg::ReceiveFRn {
-            cb_msg: Binary(vec![]),
-            purpose: None,
-            rn: rng.rand_bytes(),
-        };
-        execute(deps.as_mut(), env.clone(), info, fake_msg).unwrap();
+        generate_keys(&mut deps);
 
         // query
         let msg = QueryMsg::GetPublicKeys {};
@@ -575,28 +5546,101 @@ mod tests {
     }
 
     #[test]
-    fn test_pre_execution() {
+    fn test_config() {
         let mut deps = mock_dependencies();
         let env = mock_env();
-        let info = mock_info(OWNER, &[]);
+        let info = mock_info(SOMEBODY, &[]);
 
         // initialize
         setup_test_case(deps.as_mut()).unwrap();
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.contract_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(config.storage_version, CURRENT_VERSION);
+        assert_eq!(config.admin, Addr::unchecked(OWNER));
+        assert_eq!(config.rng_addr, Addr::unchecked(RNG_ADDR));
+        assert_eq!(config.key_gen_status, KeyGenStatus::Requested);
+        assert!(!config.keyed);
+        assert!(!config.paused);
+        assert_eq!(config.task_timeout, DEFAULT_TASK_TIMEOUT);
+        assert_eq!(config.tasks_accepted, 0);
 
-        // mock scrt-rng message
-        let mut rng = Prng::new(&[1, 2, 3], &[4, 5, 6]);
-        let fake_msg = ExecuteMsg::ReceiveFRn {
-            cb_msg: Binary(vec![]),
-            purpose: None,
-            rn: rng.rand_bytes(),
+        // the gateway is ready once keyed
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.key_gen_status, KeyGenStatus::Keyed);
+        assert!(config.keyed);
+
+        // counters follow tasks through the gateway
+        let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
+        for task_id in 1..=2 {
+            let handle_msg = ExecuteMsg::Input {
+                inputs: mock_pre_execution_msg(&gateway_pubkey, task_id),
+            };
+            execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
+        }
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.tx_cnt, 2);
+        assert_eq!(config.tasks_accepted, 2);
+        assert_eq!(config.tasks_pending, 2);
+
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[INPUT_DATA.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
         };
         execute(
             deps.as_mut(),
             env.clone(),
-            mock_info("sender", &[]),
-            fake_msg,
+            mock_info(ROUTING_INFO, &[]),
+            handle_msg,
+        )
+        .unwrap();
+        let mut late_env = mock_env();
+        late_env.block.height += DEFAULT_TASK_TIMEOUT;
+        execute(
+            deps.as_mut(),
+            late_env,
+            info,
+            ExecuteMsg::ExpireTasks { limit: None },
         )
         .unwrap();
+        let config = get_config(deps.as_ref());
+        assert_eq!(config.tasks_completed, 1);
+        assert_eq!(config.tasks_expired, 1);
+        assert_eq!(config.tasks_pending, 0);
+
+        // pause flags
+        let pause_msg = ExecuteMsg::Pause {
+            scope: PauseScope::Network {
+                network: "ethereum".to_string(),
+            },
+        };
+        execute(deps.as_mut(), env, mock_info(OWNER, &[]), pause_msg).unwrap();
+        let config = get_config(deps.as_ref());
+        assert!(!config.paused);
+        assert_eq!(config.paused_networks, 1);
+        assert_eq!(config.paused_routes, 0);
+    }
+
+    #[test]
+    fn test_pre_execution() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info(OWNER, &[]);
+
+        // initialize
+        setup_test_case(deps.as_mut()).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, OWNER);
+        register_route(&mut deps);
 
         // get gateway public encryption key
         let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
@@ -632,6 +5676,8 @@ mod tests {
             routing_code_hash: routing_code_hash.clone(),
             user_address: user_address.clone(),
             user_key: user_key.clone(),
+            gas_limit: None,
+            encrypt_result: false,
         };
         let serialized_payload = to_binary(&payload).unwrap();
 
@@ -645,7 +5691,7 @@ mod tests {
             .unwrap();
 
         // sign the payload
-        let payload_hash = sha_256(serialized_payload.as_slice());
+        let payload_hash = evm_payload_hash(&encrypted_payload);
         let message = Message::from_slice(&payload_hash).unwrap();
         let payload_signature = secp.sign_ecdsa(&message, &secret_key);
 
@@ -659,6 +5705,8 @@ mod tests {
             routing_code_hash: routing_code_hash.clone(),
             user_address: wrong_user_address.clone(),
             user_key: wrong_user_key.clone(),
+            gas_limit: None,
+            encrypt_result: false,
         };
         let wrong_serialized_payload = to_binary(&wrong_payload).unwrap();
 
@@ -667,7 +5715,7 @@ mod tests {
             .encrypt(nonce, wrong_serialized_payload.as_slice())
             .unwrap();
 
-        // test payload user_key does not match given user_key
+        // test payload hash does not commit to the given payload
         let pre_execution_msg = PreExecutionMsg {
             task_id: 1,
             handle: "test".to_string(),
@@ -678,6 +5726,7 @@ mod tests {
             user_pubkey: user_pubkey.clone(),
             payload: Binary(wrong_encrypted_payload.clone()),
             nonce: Binary(b"unique nonce".to_vec()),
+            payload_version: None,
             payload_hash: Binary(payload_hash.to_vec()),
             payload_signature: Binary(payload_signature.serialize_compact().to_vec()),
             source_network: "ethereum".to_string(),
@@ -686,20 +5735,57 @@ mod tests {
             inputs: pre_execution_msg,
         };
         let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("payload hash does not match the payload")
+        );
+
+        // sign the mock wrong payload
+        let wrong_payload_hash = evm_payload_hash(&wrong_encrypted_payload);
+        let message = Message::from_slice(&wrong_payload_hash).unwrap();
+        let wrong_payload_signature = secp.sign_ecdsa(&message, &secret_key);
+
+        // test payload user_key does not match given user_key
+        let pre_execution_msg = PreExecutionMsg {
+            task_id: 1,
+            handle: "test".to_string(),
+            routing_info: routing_info.clone(),
+            routing_code_hash: routing_code_hash.clone(),
+            user_address: user_address.clone(),
+            user_key: user_key.clone(),
+            user_pubkey: user_pubkey.clone(),
+            payload: Binary(wrong_encrypted_payload.clone()),
+            nonce: Binary(b"unique nonce".to_vec()),
+            payload_version: None,
+            payload_hash: Binary(wrong_payload_hash.to_vec()),
+            payload_signature: Binary(wrong_payload_signature.serialize_compact().to_vec()),
+            source_network: "ethereum".to_string(),
+        };
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
         assert_eq!(err, StdError::generic_err("verification key mismatch"));
 
         // wrong routing info
         let wrong_routing_info =
             Addr::unchecked("secret13rcx3p8pxf0ttuvxk6czwu73sdccfz4w6e27fd".to_string());
-        let routing_code_hash =
+        let wrong_routing_code_hash =
             "19438bf0cdf555c6472fb092eae52379c499681b36e47a2ef1c70f5269c8f02f".to_string();
 
         // test internal routing info does not match
+        // the other contract is registered, so the task gets as far as decryption
+        let route_msg = ExecuteMsg::SetRoute {
+            routing_info: wrong_routing_info.clone(),
+            code_hash: wrong_routing_code_hash.clone(),
+            handles: vec!["test".to_string()],
+        };
+        execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), route_msg).unwrap();
         let pre_execution_msg = PreExecutionMsg {
             task_id: 1u64,
             source_network: "ethereum".to_string(),
             routing_info: wrong_routing_info.clone(),
-            routing_code_hash: routing_code_hash.clone(),
+            routing_code_hash: wrong_routing_code_hash.clone(),
             payload: Binary(encrypted_payload.clone()),
             payload_hash: Binary(payload_hash.to_vec()),
             payload_signature: Binary(payload_signature.serialize_compact().to_vec()),
@@ -708,6 +5794,7 @@ mod tests {
             user_pubkey: user_pubkey.clone(),
             handle: "test".to_string(),
             nonce: Binary(b"unique nonce".to_vec()),
+            payload_version: None,
         };
         let handle_msg = ExecuteMsg::Input {
             inputs: pre_execution_msg,
@@ -726,6 +5813,7 @@ mod tests {
             user_pubkey,
             payload: Binary(encrypted_payload),
             nonce: Binary(b"unique nonce".to_vec()),
+            payload_version: None,
             payload_hash: Binary(payload_hash.to_vec()),
             payload_signature: Binary(payload_signature.serialize_compact().to_vec()),
             source_network: "ethereum".to_string(),
@@ -751,15 +5839,9 @@ mod tests {
         let info = mock_info(SOMEBODY, &[]);
         // initialize
         setup_test_case(deps.as_mut()).unwrap();
-
-        // mock scrt-rng message
-        let mut rng = Prng::new(&[1, 2, 3], &[4, 5, 6]);
-        let fake_msg = ExecuteMsg::ReceiveFRn {
-            cb_msg: Binary(vec![]),
-            purpose: None,
-            rn: rng.rand_bytes(),
-        };
-        execute(deps.as_mut(), env.clone(), info.clone(), fake_msg).unwrap();
+        generate_keys(&mut deps);
+        register_relayer(&mut deps, SOMEBODY);
+        register_route(&mut deps);
 
         // get gateway public encryption key
         let gateway_pubkey = get_gateway_encryption_key(deps.as_ref());
@@ -791,6 +5873,8 @@ mod tests {
             routing_code_hash: routing_code_hash.clone(),
             user_address: user_address.clone(),
             user_key: user_key.clone(),
+            gas_limit: None,
+            encrypt_result: false,
         };
         let serialized_payload = to_binary(&payload).unwrap();
 
@@ -804,7 +5888,7 @@ mod tests {
             .expect("encryption failure!"); // NOTE: handle this error to avoid panics!
 
         // sign the payload
-        let payload_hash = sha_256(serialized_payload.as_slice());
+        let payload_hash = evm_payload_hash(&encrypted_payload);
         let message = Message::from_slice(&payload_hash).unwrap();
         let payload_signature = secp.sign_ecdsa(&message, &secret_key);
 
@@ -812,7 +5896,7 @@ mod tests {
         let pre_execution_msg = PreExecutionMsg {
             task_id: 1u64,
             source_network: "ethereum".to_string(),
-            routing_info,
+            routing_info: routing_info.clone(),
             routing_code_hash,
             payload: Binary(encrypted_payload),
             payload_hash: Binary(payload_hash.to_vec()),
@@ -822,30 +5906,66 @@ mod tests {
             user_pubkey: user_pubkey.clone(),
             handle: "test".to_string(),
             nonce: Binary(b"unique nonce".to_vec()),
+            payload_version: None,
         };
         let handle_msg = ExecuteMsg::Input {
             inputs: pre_execution_msg.clone(),
         };
         execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap();
 
+        // results must come from the private contract the task was routed to
+        let private_contract_info = mock_info(routing_info.as_str(), &[]);
+
+        // test output from a contract other than the routing destination
+        let post_execution_msg = PostExecutionMsg {
+            result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
+            task_id: 1,
+            input_hash: Binary(
+                sha_256(&[data.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
+            ),
+        };
+        let handle_msg = ExecuteMsg::Output {
+            outputs: post_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("sender is not the private contract this task was routed to")
+        );
+
         // test incorrect input_hash
         let wrong_post_execution_msg = PostExecutionMsg {
             result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
             task_id: 1u64,
             input_hash: Binary(sha_256("wrong data".as_bytes()).to_vec()),
         };
         let handle_msg = ExecuteMsg::Output {
             outputs: wrong_post_execution_msg,
         };
-        let err = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg).unwrap_err();
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            private_contract_info.clone(),
+            handle_msg,
+        )
+        .unwrap_err();
         assert_eq!(
             err,
             StdError::generic_err("input hash does not match task id")
         );
 
+        // the rejected result leaves the task pending
+        let task_key = ("ethereum".to_string(), 1);
+        let task_info = TASK_MAP.get(&deps.storage, &task_key).unwrap();
+        let reply_id = task_info.reply_id.unwrap();
+        assert!(REPLY_TASKS.contains(&deps.storage, &reply_id));
+
         // test output handle
         let post_execution_msg = PostExecutionMsg {
             result: "{\"answer\": 42}".to_string(),
+            source_network: "ethereum".to_string(),
             task_id: 1,
             input_hash: Binary(
                 sha_256(&[data.as_bytes(), 1u64.to_le_bytes().as_ref()].concat()).to_vec(),
@@ -855,7 +5975,12 @@ mod tests {
         let handle_msg = ExecuteMsg::Output {
             outputs: post_execution_msg,
         };
-        let handle_result = execute(deps.as_mut(), env.clone(), info.clone(), handle_msg);
+        let handle_result = execute(
+            deps.as_mut(),
+            env.clone(),
+            private_contract_info.clone(),
+            handle_msg.clone(),
+        );
         assert!(
             handle_result.is_ok(),
             "handle failed: {}",
@@ -863,6 +5988,33 @@ mod tests {
         );
         let logs = handle_result.unwrap().attributes;
 
+        // the completed task and its reply entry are removed
+        assert!(!TASK_MAP.contains(&deps.storage, &task_key));
+        assert!(!REPLY_TASKS.contains(&deps.storage, &reply_id));
+
+        // results cannot be posted twice
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            private_contract_info,
+            handle_msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("task has already been completed")
+        );
+
+        // and a completed task cannot be submitted again
+        let handle_msg = ExecuteMsg::Input {
+            inputs: pre_execution_msg,
+        };
+        let err = execute(deps.as_mut(), env.clone(), info, handle_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::generic_err("task has already been completed")
+        );
+
         let gateway_pubkey = get_gateway_verification_key(deps.as_ref());
         println!("Gateway public key: {:?}", gateway_pubkey);
 
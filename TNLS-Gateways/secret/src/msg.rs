@@ -1,14 +1,23 @@
-use cosmwasm_std::{from_binary, Addr, Binary, DepsMut, StdError, StdResult};
+use cosmwasm_std::{from_binary, Addr, Binary, DepsMut, StdError, StdResult, Uint128};
 use secret_toolkit::utils::HandleCallback;
 
+use crate::contract::EVM_MESSAGE_PREFIX;
+use crate::state::KeyGenStatus;
 use crate::types::*;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use chacha20poly1305::aead::{Aead, NewAead};
-use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::aead::{self, generic_array::GenericArray, Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use hkdf::Hkdf;
 use secp256k1::{ecdh::SharedSecret, PublicKey, SecretKey};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+/// HKDF info of payload encryption keys.
+pub const PAYLOAD_KDF_INFO: &[u8] = b"TNLS payload encryption";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -16,25 +25,240 @@ pub struct InstantiateMsg {
     pub entropy: String,
     /// Optional admin address, env.message.sender if missing.
     pub admin: Option<Addr>,
+    /// Code hash of the scrt-rng contract.
     pub rng_hash: String,
+    /// Address of the scrt-rng contract, the only sender accepted for `ReceiveFRn`.
     pub rng_addr: Addr,
+    /// Optional flag to accept `Input` from any sender instead of only registered relayers,
+    /// defaults to false.
+    pub permissionless: Option<bool>,
+    /// Optional number of blocks a rotated out encryption key stays usable, defaults to
+    /// [`DEFAULT_KEY_GRACE_PERIOD`](crate::contract::DEFAULT_KEY_GRACE_PERIOD).
+    pub key_grace_period: Option<u64>,
+    /// Optional number of blocks a task can stay pending before it can be expired, defaults to
+    /// [`DEFAULT_TASK_TIMEOUT`](crate::contract::DEFAULT_TASK_TIMEOUT).
+    pub task_timeout: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    /// Address of the scrt-rng contract, required when migrating from version 0.
+    pub rng_addr: Option<Addr>,
+    /// Code hash of the scrt-rng contract, required when migrating from version 0.
+    pub rng_hash: Option<String>,
+    /// Optional flag to accept `Input` from any sender when migrating from version 0, defaults to
+    /// true like the original contract.
+    pub permissionless: Option<bool>,
+    /// Optional number of blocks a rotated out encryption key stays usable when migrating from
+    /// version 0, defaults to
+    /// [`DEFAULT_KEY_GRACE_PERIOD`](crate::contract::DEFAULT_KEY_GRACE_PERIOD).
+    pub key_grace_period: Option<u64>,
+    /// Optional number of blocks a task can stay pending when migrating from a version before 2,
+    /// defaults to [`DEFAULT_TASK_TIMEOUT`](crate::contract::DEFAULT_TASK_TIMEOUT). Tasks that are
+    /// pending at the time of the migration time out relative to the migration block.
+    pub task_timeout: Option<u64>,
+    /// Private contracts that tasks pending at the time of a migration from version 0 were sent
+    /// to, since that version did not store them.
+    #[serde(default)]
+    pub task_routes: Vec<LegacyTaskRoute>,
+}
+
+/// Destination of a task submitted before the private contract was stored with the task.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyTaskRoute {
+    /// Task ID generated by the public gateway.
+    pub task_id: u64,
+    /// Destination contract address.
+    pub routing_info: Addr,
+    /// Destination contract code hash.
+    pub routing_code_hash: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    /// Triggers the scrt-rng contract to send back previously requested randomness.
-    KeyGen { rng_hash: String, rng_addr: Addr },
+    /// Triggers the scrt-rng contract to send back previously requested randomness (admin only).
+    KeyGen {},
     /// Receives the callback message from scrt-rng. Actual key generation happens at this step.
     ReceiveFRn {
         cb_msg: Binary,
         purpose: Option<String>,
         rn: [u8; 32],
     },
-    /// Process an interchain message through the private gateway.
+    /// Requests fresh randomness from scrt-rng to rotate the gateway keys (admin only, needs
+    /// council approval while a council is set). The rotation completes once `KeyGen` is called
+    /// again.
+    RotateKeys { entropy: String },
+    /// Sets the number of blocks a rotated out encryption key stays usable (admin only, needs
+    /// council approval while a council is set).
+    SetKeyGracePeriod { blocks: u64 },
+    /// Immediately stops accepting payloads for a retired key epoch and wipes its secret keys
+    /// (admin only, needs council approval while a council is set).
+    RevokeKeyEpoch { epoch: u32 },
+    /// Adds relayers to the registry (admin only).
+    AddRelayers { relayers: Vec<Addr> },
+    /// Removes relayers from the registry, their task counters are kept (admin only).
+    RemoveRelayers { relayers: Vec<Addr> },
+    /// Switches between accepting `Input` from anyone or only from registered relayers (admin only).
+    SetPermissionless { permissionless: bool },
+    /// Sets the default number of blocks a task can stay pending before it can be expired
+    /// (admin only, needs council approval while a council is set).
+    SetTaskTimeout { blocks: u64 },
+    /// Sets the number of blocks a task sent to the given private contract can stay pending,
+    /// 0 falls back to the default (admin only, needs council approval while a council is set).
+    SetRouteTimeout { routing_info: Addr, blocks: u64 },
+    /// Sets the minimum fee in uscrt an `Input` to the given private contract has to carry
+    /// (admin only, needs council approval while a council is set).
+    SetRouteFee {
+        routing_info: Addr,
+        min_fee: Uint128,
+    },
+    /// Sends the sender's earned task fees to it.
+    ClaimFees {},
+    /// Removes up to `limit` timed out tasks and emits a signed timeout packet for each, so the
+    /// public gateway can mark them failed.
+    ExpireTasks { limit: Option<u32> },
+    /// Removes up to `limit` recorded payload nonces of key epochs that can no longer decrypt
+    /// payloads, and so can no longer be replayed. Nonces only become prunable once their epoch
+    /// has been rotated out and its grace period has passed.
+    PruneNonces { limit: Option<u32> },
+    /// Stops accepting new tasks for the given scope, tasks in flight can still post their
+    /// results (admin only, needs council approval while a council is set).
+    Pause { scope: PauseScope },
+    /// Resumes accepting new tasks for the given scope (admin only, needs council approval while
+    /// a council is set).
+    Unpause { scope: PauseScope },
+    /// Nominates a new admin that takes over once it sends `AcceptAdmin` (admin only, needs
+    /// council approval while a council is set).
+    ProposeAdmin { new_admin: Addr },
+    /// Completes an admin handover (pending admin only).
+    AcceptAdmin {},
+    /// Registers a private contract with its code hash and the handles `Input` may call,
+    /// replacing an existing registration (admin only, needs council approval while a council is
+    /// set).
+    SetRoute {
+        routing_info: Addr,
+        code_hash: String,
+        handles: Vec<String>,
+    },
+    /// Removes a private contract from the registry, tasks in flight can still post their results
+    /// (admin only, needs council approval while a council is set).
+    RemoveRoute { routing_info: Addr },
+    /// Sets how result packets sent to `network` are hashed and signed (admin only, needs council
+    /// approval while a council is set).
+    SetSigningScheme {
+        network: String,
+        scheme: SigningScheme,
+    },
+    /// Replaces the council that approves sensitive admin actions, an empty member list returns
+    /// them to the admin (admin only, needs council approval while a council is set).
+    SetCouncil { members: Vec<Addr>, threshold: u32 },
+    /// Proposes a sensitive admin action, counting as the proposer's approval (council only).
+    Propose { action: AdminAction },
+    /// Approves a proposal, which is executed once it reaches the council threshold
+    /// (council only).
+    Approve { proposal_id: u64 },
+    /// Process an interchain message through the private gateway. Attached uscrt is the task fee,
+    /// held in escrow until the task completes or refunded if it fails or expires.
     Input { inputs: PreExecutionMsg },
+    /// Process several interchain messages in one transaction. Each task is accepted or rejected
+    /// on its own, attached uscrt pays the route minimum of each accepted task in order and the
+    /// rest is returned.
+    InputBatch { inputs: Vec<PreExecutionMsg> },
     /// Receive results from private contract and broadcast logs for Relayer.
     Output { outputs: PostExecutionMsg },
+    /// Send a result that did not start on a public network to `destination_network`
+    /// (registered private contracts only). Outbound messages are numbered by the gateway apart
+    /// from tasks, their packets are received by the public gateway's `receiveOutbound`, not
+    /// `postExecution`.
+    Outbound {
+        destination_network: String,
+        result: String,
+    },
+}
+
+/// Admin actions that need council approval while a council is set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    RotateKeys {
+        entropy: String,
+    },
+    Pause {
+        scope: PauseScope,
+    },
+    Unpause {
+        scope: PauseScope,
+    },
+    ProposeAdmin {
+        new_admin: Addr,
+    },
+    SetCouncil {
+        members: Vec<Addr>,
+        threshold: u32,
+    },
+    SetRoute {
+        routing_info: Addr,
+        code_hash: String,
+        handles: Vec<String>,
+    },
+    RemoveRoute {
+        routing_info: Addr,
+    },
+    SetSigningScheme {
+        network: String,
+        scheme: SigningScheme,
+    },
+    SetKeyGracePeriod {
+        blocks: u64,
+    },
+    RevokeKeyEpoch {
+        epoch: u32,
+    },
+    SetTaskTimeout {
+        blocks: u64,
+    },
+    SetRouteTimeout {
+        routing_info: Addr,
+        blocks: u64,
+    },
+    SetRouteFee {
+        routing_info: Addr,
+        min_fee: Uint128,
+    },
+}
+
+/// Traffic affected by `Pause` and `Unpause`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseScope {
+    /// Every source network and route.
+    All,
+    /// Tasks coming from a single public network and outbound messages sent to it.
+    Network { network: String },
+    /// Tasks routed to a single private contract and outbound messages it sends.
+    Route { routing_info: Addr },
+}
+
+/// Format in which result packets for a destination network are hashed and signed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SigningScheme {
+    /// Keccak256 hashes behind the `\x19Ethereum Signed Message` prefix, signed with secp256k1
+    /// for `ecrecover`. Used for networks without a scheme.
+    EvmPersonalSign,
+    /// EIP-712 `Result` and `Packet` structs in the domain of the public gateway, signed with
+    /// secp256k1 for `ecrecover`.
+    Eip712 {
+        /// Chain ID of the destination network.
+        chain_id: u64,
+        /// '0x' prefixed address of the public gateway contract.
+        verifying_contract: String,
+    },
+    /// SHA-256 hashes signed with secp256k1, as verified by Cosmos chains.
+    Secp256k1Sha256,
+    /// SHA-256 hashes signed with the gateway's ed25519 key, as verified by Solana and NEAR.
+    Ed25519,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
@@ -50,11 +274,86 @@ pub struct InputResponse {
     pub status: ResponseStatus,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InputBatchResponse {
+    /// Outcome of each task, in the order of the batch.
+    pub results: Vec<BatchInputResult>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BatchInputResult {
+    /// The name of the network that task came from.
+    pub source_network: String,
+    /// Task ID generated by the public gateway.
+    pub task_id: u64,
+    /// Whether the task was sent to its private contract.
+    pub status: ResponseStatus,
+    /// Reason the task was rejected.
+    pub error: Option<String>,
+}
+
+/// Result sent back to the public network for a task the private contract did not complete.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FailureResult {
+    pub status: ResponseStatus,
+    /// Machine readable reason the task failed.
+    pub code: FailureCode,
+    /// Reason the task failed.
+    pub error: String,
+}
+
+/// Reasons a task can fail without results from the private contract.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCode {
+    /// The private contract call returned an error or ran out of gas.
+    ExecutionFailed,
+    /// The private contract did not post results before the task timed out.
+    Timeout,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    /// Query the gateway's public keys.
+    /// Query the gateway's current public keys.
     GetPublicKeys {},
+    /// Query the gateway's public keys for a given key epoch, including retired ones.
+    GetEpochPublicKeys { epoch: u32 },
+    /// Query the progress of gateway key generation.
+    GetKeyGenStatus {},
+    /// Query the relayer registry and per-relayer task counters.
+    GetRelayers {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    /// Query which source networks and routes are paused.
+    GetPauseStatus {},
+    /// Query the admin, a pending admin handover and the council.
+    GetAdmin {},
+    /// Query the gateway configuration, versions and task counters.
+    GetConfig {},
+    /// Query the private contracts and handles the gateway routes tasks to.
+    GetRoutes {
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    /// Query a proposal made to the council.
+    GetProposal { proposal_id: u64 },
+    /// Query how result packets sent to a network are hashed and signed.
+    GetSigningScheme { network: String },
+    /// Query what happened to a task, without revealing its payload or inputs.
+    GetTaskStatus {
+        source_network: String,
+        task_id: u64,
+    },
+    /// Query pending tasks, optionally only those from a source network or sent to a private
+    /// contract. Pages are counted after filtering.
+    GetPendingTasks {
+        source_network: Option<String>,
+        routing_info: Option<Addr>,
+        page: Option<u32>,
+        page_size: Option<u32>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -63,6 +362,199 @@ pub struct PublicKeyResponse {
     pub encryption_key: Binary,
     /// '0x' prefixed hex encoded byte string.
     pub verification_key: String,
+    /// '0x' prefixed hex encoded ed25519 key used for networks with the `ed25519` signing scheme,
+    /// `None` before key generation and for retired epochs.
+    pub ed25519_verification_key: Option<String>,
+    /// Key epoch these keys belong to.
+    pub key_epoch: u32,
+    /// Block height from which the encryption key is no longer accepted, `None` for the current epoch.
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeyGenStatusResponse {
+    /// Current stage of key generation.
+    pub status: KeyGenStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayersResponse {
+    /// Whether `Input` is accepted from senders outside the registry.
+    pub permissionless: bool,
+    /// Registered relayers and any other senders that have submitted tasks.
+    pub relayers: Vec<RelayerStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    /// Version of the contract code.
+    pub contract_version: String,
+    /// Version of the storage layout.
+    pub storage_version: u32,
+    /// Current admin address.
+    pub admin: Addr,
+    /// Address of the scrt-rng contract trusted for key generation entropy.
+    pub rng_addr: Addr,
+    /// Code hash of the scrt-rng contract.
+    pub rng_hash: String,
+    /// Current stage of key generation.
+    pub key_gen_status: KeyGenStatus,
+    /// Whether the gateway keys have been created.
+    pub keyed: bool,
+    /// Epoch of the current key pairs.
+    pub key_epoch: u32,
+    /// Number of blocks a retired encryption key keeps decrypting payloads after a rotation.
+    pub key_grace_period: u64,
+    /// Whether `Input` is accepted from senders outside the registry.
+    pub permissionless: bool,
+    /// Whether all traffic is paused.
+    pub paused: bool,
+    /// Number of public networks whose traffic is paused.
+    pub paused_networks: u32,
+    /// Number of private contracts whose tasks are paused.
+    pub paused_routes: u32,
+    /// Default number of blocks a task can stay pending.
+    pub task_timeout: u64,
+    /// Number of private contract calls made.
+    pub tx_cnt: u64,
+    /// Number of outbound messages sent.
+    pub outbound_cnt: u64,
+    /// Number of tasks accepted through `Input`.
+    pub tasks_accepted: u64,
+    /// Number of tasks waiting for results from the private contract.
+    pub tasks_pending: u64,
+    /// Number of tasks whose results were posted by the private contract.
+    pub tasks_completed: u64,
+    /// Number of tasks whose private contract call failed.
+    pub tasks_failed: u64,
+    /// Number of tasks that timed out.
+    pub tasks_expired: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SigningSchemeResponse {
+    /// Destination network.
+    pub network: String,
+    /// How result packets sent to the network are hashed and signed.
+    pub scheme: SigningScheme,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoutesResponse {
+    /// Registered private contracts on the requested page.
+    pub routes: Vec<RouteResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RouteResponse {
+    /// Private contract address.
+    pub routing_info: Addr,
+    /// Code hash the private contract is pinned to.
+    pub code_hash: String,
+    /// Handles `Input` may call on the private contract.
+    pub handles: Vec<String>,
+    /// Minimum fee in uscrt an `Input` to the private contract has to carry.
+    pub min_fee: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AdminResponse {
+    /// Current admin address.
+    pub admin: Addr,
+    /// Address nominated to take over as admin.
+    pub pending_admin: Option<Addr>,
+    /// Council approving sensitive admin actions, `None` in single admin mode.
+    pub council: Option<CouncilResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CouncilResponse {
+    /// Council member addresses.
+    pub members: Vec<Addr>,
+    /// Number of member approvals needed to execute a proposal.
+    pub threshold: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalResponse {
+    /// Proposed admin action.
+    pub action: AdminAction,
+    /// Council members that approved the proposal.
+    pub approvals: Vec<Addr>,
+    /// Whether the action has been executed.
+    pub executed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PauseStatusResponse {
+    /// Whether all traffic is paused.
+    pub paused: bool,
+    /// Public networks whose traffic is paused.
+    pub paused_networks: Vec<String>,
+    /// Private contracts whose tasks are paused.
+    pub paused_routes: Vec<Addr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TaskStatusResponse {
+    /// Current status of the task.
+    pub status: TaskStatus,
+    /// Block height at which the task entered its current status, `None` for unknown tasks.
+    pub updated_at: Option<u64>,
+    /// The original payload_hash from the front-end, `None` for unknown tasks.
+    pub payload_hash: Option<Binary>,
+    /// Hash of the result sent to the public network, `None` until the task is closed.
+    pub output_hash: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTasksResponse {
+    /// Pending tasks on the requested page.
+    pub tasks: Vec<PendingTask>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTask {
+    /// The name of the network that task came from.
+    pub source_network: String,
+    /// Task ID generated by the public gateway.
+    pub task_id: u64,
+    /// Private contract the task was sent to.
+    pub routing_info: Addr,
+    /// Block height at which the task was accepted.
+    pub accepted_height: u64,
+    /// Block height from which the task can be expired.
+    pub expires_at: u64,
+}
+
+/// Stages of a task as seen by the secret gateway.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    /// The gateway has not accepted a task with this ID from the source network.
+    Unknown,
+    /// The task was sent to the private contract, which has not posted results yet.
+    Pending,
+    /// The private contract posted its results.
+    Completed,
+    /// The private contract call failed.
+    Failed,
+    /// The task timed out before the private contract posted results.
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RelayerStatus {
+    /// Relayer address.
+    pub address: Addr,
+    /// Whether the relayer is currently in the registry.
+    pub registered: bool,
+    /// Number of tasks the relayer submitted.
+    pub tasks_submitted: u64,
+    /// Number of submitted tasks that have completed.
+    pub tasks_completed: u64,
+    /// Fees in uscrt earned by completed tasks and not yet claimed.
+    pub claimable_fees: Uint128,
 }
 
 /// Message received from the relayer.
@@ -78,7 +570,7 @@ pub struct PreExecutionMsg {
     pub routing_code_hash: String,
     /// Encryption of (data, routing info, and user info).
     pub payload: Binary,
-    /// Hash of encrypted input values.
+    /// Hash of encrypted input values, see [`evm_payload_hash`].
     pub payload_hash: Binary,
     /// Signature of hash of encrypted input values.
     pub payload_signature: Binary,
@@ -92,10 +584,18 @@ pub struct PreExecutionMsg {
     pub handle: String,
     /// Unique random bytes used to encrypt payload.
     pub nonce: Binary,
+    /// Encryption format of the payload, see [`PayloadVersion`]. Version 1 if missing.
+    pub payload_version: Option<u8>,
 }
 
 impl PreExecutionMsg {
     pub fn verify(&self, deps: &DepsMut) -> StdResult<()> {
+        // the signed hash has to commit to the payload that gets decrypted
+        if self.payload_hash.as_slice() != evm_payload_hash(self.payload.as_slice()) {
+            return Err(StdError::generic_err(
+                "payload hash does not match the payload",
+            ));
+        }
         deps.api
             .secp256k1_verify(
                 self.payload_hash.as_slice(),
@@ -105,24 +605,244 @@ impl PreExecutionMsg {
             .map_err(|err| StdError::generic_err(err.to_string()))?;
         Ok(())
     }
-    pub fn decrypt_payload(&self, sk: Binary) -> StdResult<Payload> {
+    pub fn payload_version(&self) -> StdResult<PayloadVersion> {
+        PayloadVersion::new(self.payload_version.unwrap_or(1))
+    }
+    pub fn payload_key(&self, sk: Binary) -> StdResult<Binary> {
         let my_secret = SecretKey::from_slice(sk.as_slice())
             .map_err(|err| StdError::generic_err(err.to_string()))?;
         let their_public = PublicKey::from_slice(self.user_key.as_slice())
             .map_err(|err| StdError::generic_err(err.to_string()))?;
         let shared_key = SharedSecret::new(&their_public, &my_secret);
-        let cipher = ChaCha20Poly1305::new_from_slice(shared_key.as_ref())
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
-        let nonce = Nonce::from_slice(self.nonce.as_slice());
-        let plaintext = cipher
-            .decrypt(nonce, self.payload.as_slice())
-            .map(Binary)
-            .map_err(|err| StdError::generic_err(err.to_string()))?;
-        let payload: Payload = from_binary(&plaintext)?;
+        self.payload_version()?.derive_key(shared_key.as_ref())
+    }
+    /// Returns Vec<u8>
+    ///
+    /// task metadata the payload is bound to by versions with `bind_metadata`: the handle,
+    /// routing_code_hash, source_network and user_address, each prefixed with its length in bytes
+    /// as a 4 byte big-endian integer.
+    pub fn associated_data(&self) -> Vec<u8> {
+        let mut aad = vec![];
+        for field in [
+            self.handle.as_str(),
+            self.routing_code_hash.as_str(),
+            self.source_network.as_str(),
+            self.user_address.as_str(),
+        ] {
+            aad.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            aad.extend_from_slice(field.as_bytes());
+        }
+        aad
+    }
+    pub fn decrypt_payload(&self, sk: Binary) -> StdResult<Payload> {
+        let payload_key = self.payload_key(sk)?;
+        let version = self.payload_version()?;
+        let aad = match version.bind_metadata {
+            true => self.associated_data(),
+            false => vec![],
+        };
+        let plaintext = version.decrypt(
+            payload_key.as_slice(),
+            self.nonce.as_slice(),
+            self.payload.as_slice(),
+            &aad,
+        )?;
+        let payload: Payload = from_binary(&Binary(plaintext))?;
         Ok(payload)
     }
 }
 
+/// Returns [u8; 32]
+///
+/// hashes an encrypted payload the way users sign it: the Ethereum signed message hash of its
+/// Keccak256 hash, which is also what `Gateway.sol` recovers the user address from.
+pub fn evm_payload_hash(payload: &[u8]) -> [u8; 32] {
+    let hash = Keccak256::digest(payload);
+    Keccak256::digest([EVM_MESSAGE_PREFIX, &hash].concat()).into()
+}
+
+/// AEADs a payload can be encrypted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadCipher {
+    /// ChaCha20-Poly1305 with 12 byte nonces.
+    ChaCha20Poly1305,
+    /// XChaCha20-Poly1305 with 24 byte nonces, long enough to be picked at random.
+    XChaCha20Poly1305,
+    /// AES-256-GCM-SIV with 12 byte nonces, which does not break down when a nonce is reused.
+    Aes256GcmSiv,
+}
+
+/// Key derivations applied to the ECDH shared secret to get the AEAD key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadKdf {
+    /// The shared secret is the key.
+    Raw,
+    /// HKDF-SHA256 of the shared secret, without salt and with [`PAYLOAD_KDF_INFO`] as info.
+    HkdfSha256,
+}
+
+/// Encryption format of a payload, selected by `PreExecutionMsg::payload_version`.
+///
+/// | version | AEAD               | KDF         | associated data |
+/// |---------|--------------------|-------------|-----------------|
+/// | 1       | ChaCha20-Poly1305  | raw         | none            |
+/// | 2       | ChaCha20-Poly1305  | HKDF-SHA256 | none            |
+/// | 3       | XChaCha20-Poly1305 | HKDF-SHA256 | none            |
+/// | 4       | AES-256-GCM-SIV    | HKDF-SHA256 | none            |
+/// | 5       | ChaCha20-Poly1305  | HKDF-SHA256 | task metadata   |
+/// | 6       | XChaCha20-Poly1305 | HKDF-SHA256 | task metadata   |
+/// | 7       | AES-256-GCM-SIV    | HKDF-SHA256 | task metadata   |
+///
+/// Task metadata is `PreExecutionMsg::associated_data`, so a relayer cannot pair the payload with
+/// another handle, destination code hash, source network or user address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PayloadVersion {
+    /// Version number sent by the client.
+    pub version: u8,
+    /// AEAD the payload is encrypted with.
+    pub cipher: PayloadCipher,
+    /// Key derivation applied to the shared secret.
+    pub kdf: PayloadKdf,
+    /// Whether the AEAD tag covers the task metadata.
+    pub bind_metadata: bool,
+}
+
+impl PayloadVersion {
+    pub fn new(version: u8) -> StdResult<Self> {
+        let (cipher, kdf, bind_metadata) = match version {
+            1 => (PayloadCipher::ChaCha20Poly1305, PayloadKdf::Raw, false),
+            2 => (
+                PayloadCipher::ChaCha20Poly1305,
+                PayloadKdf::HkdfSha256,
+                false,
+            ),
+            3 => (
+                PayloadCipher::XChaCha20Poly1305,
+                PayloadKdf::HkdfSha256,
+                false,
+            ),
+            4 => (PayloadCipher::Aes256GcmSiv, PayloadKdf::HkdfSha256, false),
+            5 => (
+                PayloadCipher::ChaCha20Poly1305,
+                PayloadKdf::HkdfSha256,
+                true,
+            ),
+            6 => (
+                PayloadCipher::XChaCha20Poly1305,
+                PayloadKdf::HkdfSha256,
+                true,
+            ),
+            7 => (PayloadCipher::Aes256GcmSiv, PayloadKdf::HkdfSha256, true),
+            _ => {
+                return Err(StdError::generic_err(format!(
+                    "unsupported payload version {}",
+                    version
+                )))
+            }
+        };
+        Ok(PayloadVersion {
+            version,
+            cipher,
+            kdf,
+            bind_metadata,
+        })
+    }
+    pub fn nonce_len(&self) -> usize {
+        match self.cipher {
+            PayloadCipher::ChaCha20Poly1305 | PayloadCipher::Aes256GcmSiv => 12,
+            PayloadCipher::XChaCha20Poly1305 => 24,
+        }
+    }
+    pub fn derive_key(&self, shared_secret: &[u8]) -> StdResult<Binary> {
+        match self.kdf {
+            PayloadKdf::Raw => Ok(Binary(shared_secret.to_vec())),
+            PayloadKdf::HkdfSha256 => {
+                let mut key = [0u8; 32];
+                Hkdf::<Sha256>::new(None, shared_secret)
+                    .expand(PAYLOAD_KDF_INFO, &mut key)
+                    .map_err(|err| StdError::generic_err(err.to_string()))?;
+                Ok(Binary(key.to_vec()))
+            }
+        }
+    }
+    pub fn encrypt(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        plaintext: &[u8],
+        aad: &[u8],
+    ) -> StdResult<Vec<u8>> {
+        self.check_nonce(nonce)?;
+        let payload = aead::Payload {
+            msg: plaintext,
+            aad,
+        };
+        match self.cipher {
+            PayloadCipher::ChaCha20Poly1305 => {
+                aead_encrypt::<ChaCha20Poly1305>(key, nonce, payload)
+            }
+            PayloadCipher::XChaCha20Poly1305 => {
+                aead_encrypt::<XChaCha20Poly1305>(key, nonce, payload)
+            }
+            PayloadCipher::Aes256GcmSiv => aead_encrypt::<Aes256GcmSiv>(key, nonce, payload),
+        }
+    }
+    pub fn decrypt(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        ciphertext: &[u8],
+        aad: &[u8],
+    ) -> StdResult<Vec<u8>> {
+        self.check_nonce(nonce)?;
+        let payload = aead::Payload {
+            msg: ciphertext,
+            aad,
+        };
+        match self.cipher {
+            PayloadCipher::ChaCha20Poly1305 => {
+                aead_decrypt::<ChaCha20Poly1305>(key, nonce, payload)
+            }
+            PayloadCipher::XChaCha20Poly1305 => {
+                aead_decrypt::<XChaCha20Poly1305>(key, nonce, payload)
+            }
+            PayloadCipher::Aes256GcmSiv => aead_decrypt::<Aes256GcmSiv>(key, nonce, payload),
+        }
+    }
+    fn check_nonce(&self, nonce: &[u8]) -> StdResult<()> {
+        if nonce.len() != self.nonce_len() {
+            return Err(StdError::generic_err(format!(
+                "payload version {} needs a {} byte nonce",
+                self.version,
+                self.nonce_len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn aead_encrypt<A: KeyInit + Aead>(
+    key: &[u8],
+    nonce: &[u8],
+    payload: aead::Payload,
+) -> StdResult<Vec<u8>> {
+    let cipher = A::new_from_slice(key).map_err(|err| StdError::generic_err(err.to_string()))?;
+    cipher
+        .encrypt(GenericArray::from_slice(nonce), payload)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
+fn aead_decrypt<A: KeyInit + Aead>(
+    key: &[u8],
+    nonce: &[u8],
+    payload: aead::Payload,
+) -> StdResult<Vec<u8>> {
+    let cipher = A::new_from_slice(key).map_err(|err| StdError::generic_err(err.to_string()))?;
+    cipher
+        .decrypt(GenericArray::from_slice(nonce), payload)
+        .map_err(|err| StdError::generic_err(err.to_string()))
+}
+
 /// Messages sent to other secret contracts.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
@@ -156,6 +876,8 @@ pub struct PrivContractHandleMsg {
     pub handle: String,
     /// Public network user address.
     pub user_address: Addr,
+    /// Network the task came from, must be passed back in `PostExecutionMsg`.
+    pub source_network: String,
     /// Task ID passed along for later verification.
     pub task_id: u64,
     /// SHA256 hash of `input_values`.
@@ -169,6 +891,8 @@ pub struct PrivContractHandleMsg {
 pub struct PostExecutionMsg {
     /// JSON string of results from the private contract.
     pub result: String,
+    /// Network the task came from, together with `task_id` identifies the task.
+    pub source_network: String,
     /// Task ID from private contract for verification.
     pub task_id: u64,
     /// SHA256 of decrypted (inputs + task ID) for verification.
@@ -1,34 +1,185 @@
-use cosmwasm_std::{Addr, Binary, CanonicalAddr};
-use secret_toolkit::storage::{Item, Keymap};
+use cosmwasm_std::{Addr, Binary, CanonicalAddr, Uint128};
+use secret_toolkit::{
+    serialization::Json,
+    storage::{Item, Keymap},
+};
+
+use crate::msg::{AdminAction, SigningScheme};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+// Records that gain fields over time are stored as JSON, so a field added with
+// `#[serde(default)]` can still be read from entries written by an older version.
+
+/// Declares `Keymap` storage keys.
+///
+/// A `Keymap` caches its length, which goes stale once a unit test starts over with fresh mock
+/// storage, so in unit tests every test thread gets its own instance of each keymap.
+///
+/// `Keymap::remove` in secret-toolkit 0.7 returns early without deleting the entry of the last key
+/// in its index, so `get` and `contains` would still find it. The declared keymaps remove through
+/// a wrapper that deletes the entry as well.
+macro_rules! keymaps {
+    ($($(#[$attr:meta])* pub static $name:ident: Keymap<$key:ty, $value:ty $(, $ser:ty)?> = $namespace:expr;)+) => {
+        $(
+            $(#[$attr])*
+            #[allow(non_camel_case_types)]
+            pub struct $name;
+
+            #[cfg(not(test))]
+            impl std::ops::Deref for $name {
+                type Target = Keymap<'static, $key, $value $(, $ser)?>;
+
+                fn deref(&self) -> &Self::Target {
+                    static KEYMAP: Keymap<$key, $value $(, $ser)?> = Keymap::new($namespace);
+                    &KEYMAP
+                }
+            }
+
+            #[cfg(test)]
+            impl std::ops::Deref for $name {
+                type Target = Keymap<'static, $key, $value $(, $ser)?>;
+
+                fn deref(&self) -> &Self::Target {
+                    thread_local! {
+                        static KEYMAP: &'static Keymap<'static, $key, $value $(, $ser)?> =
+                            Box::leak(Box::new(Keymap::new($namespace)));
+                    }
+                    KEYMAP.with(|keymap| *keymap)
+                }
+            }
+
+            impl $name {
+                /// Removes the entry stored under `key`.
+                #[allow(dead_code)]
+                pub fn remove(
+                    &self,
+                    storage: &mut dyn cosmwasm_std::Storage,
+                    key: &$key,
+                ) -> cosmwasm_std::StdResult<()> {
+                    use secret_toolkit::serialization::Serde;
+                    type Ser = keymap_serde!($($ser)?);
+
+                    (**self).remove(storage, key)?;
+                    let key = Ser::serialize(key)?;
+                    storage.remove(&[&$namespace[..], &key].concat());
+                    Ok(())
+                }
+            }
+        )+
+    };
+}
+
+/// Names the serialization of a `Keymap`, `Bincode2` unless one is given.
+macro_rules! keymap_serde {
+    () => {
+        secret_toolkit::serialization::Bincode2
+    };
+    ($ser:ty) => {
+        $ser
+    };
+}
+
+/// Version of the storage layout written by this code.
+pub const CURRENT_VERSION: u32 = 3;
+
+/// Storage key for the version of the storage layout, missing for contracts deployed before it
+/// was versioned.
+pub static VERSION: Item<u32> = Item::new(b"version");
 /// Storage key for this contract's configuration.
-pub static CONFIG: Item<State> = Item::new(b"config");
+pub static CONFIG: Item<State, Json> = Item::new(b"config");
 /// Storage key for this contract's address.
 pub static MY_ADDRESS: Item<CanonicalAddr> = Item::new(b"myaddr");
 /// Storage key for the contract instantiator.
 pub static CREATOR: Item<CanonicalAddr> = Item::new(b"creator");
+/// Storage key for the admin council, absent while a single admin is in charge.
+pub static COUNCIL: Item<Council, Json> = Item::new(b"council");
 /// Storage key for prng seed.
 pub static PRNG_SEED: Item<Vec<u8>> = Item::new(b"prngseed");
-/// Storage key for task IDs.
-pub static TASK_MAP: Keymap<u64, TaskInfo> = Keymap::new(b"tasks");
+/// Storage key for gateway wide task counters.
+pub static TASK_COUNTERS: Item<TaskCounters, Json> = Item::new(b"taskcounters");
+keymaps! {
+    /// Storage key for admin actions proposed to the council.
+    pub static PROPOSALS: Keymap<u64, Proposal, Json> = b"proposals";
+    /// Storage key for retired key epochs.
+    pub static KEY_HISTORY: Keymap<u32, KeyEpoch, Json> = b"keyhistory";
+    /// Storage key for public networks whose traffic is paused.
+    pub static PAUSED_NETWORKS: Keymap<String, bool> = b"pausednetworks";
+    /// Storage key for private contracts whose tasks are paused.
+    pub static PAUSED_ROUTES: Keymap<Addr, bool> = b"pausedroutes";
+    /// Storage key for the registry of private contracts tasks can be routed to.
+    pub static ROUTES: Keymap<Addr, RouteInfo, Json> = b"routes";
+    /// Storage key for the signing scheme of each destination network, EVM personal-sign if
+    /// missing.
+    pub static SIGNING_SCHEMES: Keymap<String, SigningScheme, Json> = b"signingschemes";
+    /// Storage key for per-route minimum task fees in uscrt.
+    pub static ROUTE_FEES: Keymap<Addr, Uint128> = b"routefees";
+    /// Storage key for per-route task timeouts in blocks, 0 falls back to the gateway default.
+    pub static ROUTE_TIMEOUTS: Keymap<Addr, u64> = b"routetimeouts";
+    /// Storage key for the relayer registry and per-relayer task counters.
+    pub static RELAYERS: Keymap<Addr, RelayerInfo, Json> = b"relayers";
+    /// Storage key for pending tasks.
+    pub static TASK_MAP: Keymap<TaskKey, TaskInfo, Json> = b"networktasks";
+    /// Storage key for tasks that are no longer pending, kept to reject replays.
+    pub static CLOSED_TASKS: Keymap<TaskKey, ClosedTask, Json> = b"closedtasks";
+    /// Storage key for the SHA256 hash of `user_key || nonce` of every accepted payload, mapped to
+    /// the key epoch that decrypted it. Entries are pruned once their epoch stops decrypting
+    /// payloads, so nothing becomes prunable until the keys are rotated: the map grows with every
+    /// task accepted in the current and previous epoch, and regular key rotation is what bounds it.
+    pub static SEEN_NONCES: Keymap<[u8; 32], u32> = b"seennonces";
+    /// Storage key for the task each private contract call was made for, by reply ID. Entries are
+    /// only read by the reply in the transaction that wrote them, and removed with their task.
+    pub static REPLY_TASKS: Keymap<u64, ReplyTask, Json> = b"replytasks";
+}
+
+/// Identifies a task by the network it came from and the task ID assigned by that network.
+pub type TaskKey = (String, u64);
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     /// Admin adress.
     pub admin: CanonicalAddr,
-    /// Status of gateway key generation.
-    pub keyed: bool,
-    /// Count of tx.
+    /// Address nominated to take over as admin once it accepts.
+    pub pending_admin: Option<CanonicalAddr>,
+    /// Address of the scrt-rng contract trusted for key generation entropy.
+    pub rng_addr: CanonicalAddr,
+    /// Code hash of the scrt-rng contract.
+    pub rng_hash: String,
+    /// Progress of the key generation lifecycle.
+    pub key_gen_status: KeyGenStatus,
+    /// Epoch of the current key pairs, incremented on every rotation.
+    pub key_epoch: u32,
+    /// Number of blocks a retired encryption key keeps decrypting payloads after a rotation.
+    pub key_grace_period: u64,
+    /// Whether `Input` is accepted from any sender instead of only registered relayers.
+    pub permissionless: bool,
+    /// Whether `Input` is rejected for every source network and route.
+    pub paused: bool,
+    /// Number of blocks a task can stay pending before it can be expired, unless its route sets
+    /// its own timeout.
+    #[serde(default)]
+    pub task_timeout: u64,
+    /// Count of private contract calls, used as their reply ID.
     pub tx_cnt: u64,
+    /// Count of outbound messages, used as their outbound ID. Public gateways keep these IDs apart
+    /// from the task IDs they assign themselves.
+    #[serde(default)]
+    pub outbound_cnt: u64,
     /// Private gateway encryption key pair.
     pub encryption_keys: KeyPair,
     /// Private gateway signing key pair.
     pub signing_keys: KeyPair,
 }
 
+impl State {
+    /// Whether the gateway keys have been created. A rotation moves `key_gen_status` back to
+    /// requested, the current keys stay in use until it completes.
+    pub fn keyed(&self) -> bool {
+        !self.signing_keys.pk.is_empty()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TaskInfo {
     /// The original, encrypted payload.
@@ -41,7 +192,157 @@ pub struct TaskInfo {
     pub source_network: String,
     /// Public address of the user that sent the message.
     pub user_address: Addr,
+    /// Private contract the task was dispatched to, the only sender allowed to post its output.
+    pub routing_info: Addr,
+    /// Code hash of the private contract the task was dispatched to.
+    pub routing_code_hash: String,
+    /// Key epoch of the encryption key that decrypted the payload.
+    pub key_epoch: u32,
+    /// Relayer that submitted the task.
+    pub relayer: Addr,
+    /// Block height at which the task was accepted.
+    #[serde(default)]
+    pub accepted_height: u64,
+    /// Block time in seconds at which the task was accepted.
+    #[serde(default)]
+    pub accepted_time: u64,
+    /// Block height from which the task can be expired.
+    #[serde(default)]
+    pub expires_at: u64,
+    /// Fee in uscrt held in escrow until the task is closed.
+    #[serde(default)]
+    pub fee: Uint128,
+    /// Key shared with the user the result is encrypted with, `None` for plaintext results.
+    #[serde(default)]
+    pub result_key: Option<Binary>,
+    /// Encryption format of the payload, also used for the result. Version 1 if missing.
+    #[serde(default)]
+    pub payload_version: Option<u8>,
+    /// Reply ID of the private contract call, `None` for tasks accepted before calls replied.
+    #[serde(default)]
+    pub reply_id: Option<u64>,
+}
+
+/// Task a private contract call was made for.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReplyTask {
+    /// Source network and task ID of the task.
+    pub task_key: TaskKey,
+    /// Whether the task was sent in an `InputBatch`.
+    pub batched: bool,
+}
+
+/// Record of a task that has left the pending map.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClosedTask {
+    /// How the task was closed.
+    pub outcome: TaskOutcome,
+    /// Block height at which the task was closed.
+    pub closed_at: u64,
+    /// The original payload_hash from the front-end.
+    #[serde(default)]
+    pub payload_hash: Binary,
+    /// Hash of the result sent to the public network, as signed in the result packet.
+    #[serde(default)]
+    pub output_hash: Binary,
+}
+
+/// Ways a task can leave the pending map.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutcome {
+    /// The private contract posted its results through `Output`.
+    Completed,
+    /// The task timed out and was removed by `ExpireTasks`.
+    Expired,
+    /// The private contract call failed and the gateway posted a failure result.
+    Failed,
+}
+
+/// Number of tasks the gateway has accepted and closed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TaskCounters {
+    /// Number of tasks accepted through `Input`.
+    pub accepted: u64,
+    /// Number of tasks whose results were posted by the private contract.
+    pub completed: u64,
+    /// Number of tasks whose private contract call failed.
+    pub failed: u64,
+    /// Number of tasks removed by `ExpireTasks`.
+    pub expired: u64,
 }
+
+/// Private contract registered as a task destination.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RouteInfo {
+    /// Code hash the private contract is pinned to.
+    pub code_hash: String,
+    /// Handles `Input` may call on the private contract.
+    pub handles: Vec<String>,
+}
+
+/// Registration status and task counters of a relayer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct RelayerInfo {
+    /// Whether the relayer is currently in the registry.
+    pub registered: bool,
+    /// Number of tasks the relayer submitted through `Input`.
+    pub tasks_submitted: u64,
+    /// Number of submitted tasks whose results were posted by the private contract.
+    pub tasks_completed: u64,
+    /// Fees in uscrt earned by completed tasks and not yet claimed.
+    #[serde(default)]
+    pub claimable_fees: Uint128,
+}
+
+/// Set of admin addresses whose approvals are needed for sensitive admin actions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Council {
+    /// Council member addresses.
+    pub members: Vec<CanonicalAddr>,
+    /// Number of member approvals needed to execute a proposal.
+    pub threshold: u32,
+    /// ID of the first proposal made to this council, older proposals can no longer be approved.
+    pub first_proposal: u64,
+}
+
+/// Admin action awaiting council approval.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    /// Action executed once the proposal has enough approvals.
+    pub action: AdminAction,
+    /// Council members that approved the proposal, starting with the proposer.
+    pub approvals: Vec<CanonicalAddr>,
+    /// Whether the action has been executed.
+    pub executed: bool,
+}
+
+/// Key pairs of a key epoch that has been rotated out.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KeyEpoch {
+    /// Retired encryption key pair, the secret key is wiped once it can no longer be used.
+    pub encryption_keys: KeyPair,
+    /// Retired signing key pair, signs the results of tasks accepted under this epoch until it
+    /// expires. The secret key is wiped once it can no longer be used.
+    pub signing_keys: KeyPair,
+    /// Block height at which the epoch was rotated out.
+    pub retired_at: u64,
+    /// Block height from which payloads encrypted to this epoch are rejected.
+    pub expires_at: u64,
+}
+
+/// Stages of gateway key generation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyGenStatus {
+    /// Randomness has been requested from scrt-rng, waiting for `KeyGen`.
+    Requested,
+    /// `KeyGen` has asked scrt-rng to send the randomness back, waiting for `ReceiveFRn`.
+    Fulfilled,
+    /// Randomness has been received and the gateway keys were created.
+    Keyed,
+}
+
 /// A key pair using the [Binary] type
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct KeyPair {
@@ -50,3 +351,58 @@ pub struct KeyPair {
     /// Public key part of the key pair.
     pub pk: Binary,
 }
+
+impl KeyPair {
+    /// Returns a copy of the key pair without its secret key.
+    pub fn public_only(&self) -> KeyPair {
+        KeyPair {
+            sk: Binary::default(),
+            pk: self.pk.clone(),
+        }
+    }
+}
+
+/// Storage layout of contracts deployed before the layout was versioned, only read by `migrate`.
+pub mod v0 {
+    use cosmwasm_std::{Addr, Binary, CanonicalAddr};
+    use secret_toolkit::storage::{Item, Keymap};
+
+    use serde::{Deserialize, Serialize};
+
+    use super::KeyPair;
+
+    /// Storage key for this contract's configuration.
+    pub static CONFIG: Item<State> = Item::new(b"config");
+    keymaps! {
+        /// Storage key for task IDs.
+        pub static TASK_MAP: Keymap<u64, TaskInfo> = b"tasks";
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct State {
+        /// Admin adress.
+        pub admin: CanonicalAddr,
+        /// Status of gateway key generation.
+        pub keyed: bool,
+        /// Count of tx.
+        pub tx_cnt: u64,
+        /// Private gateway encryption key pair.
+        pub encryption_keys: KeyPair,
+        /// Private gateway signing key pair.
+        pub signing_keys: KeyPair,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+    pub struct TaskInfo {
+        /// The original, encrypted payload.
+        pub payload: Binary,
+        /// The original payload_hash from the front-end.
+        pub payload_hash: Binary,
+        /// A unique hash for the task.
+        pub input_hash: [u8; 32],
+        /// The name of the network that message came from.
+        pub source_network: String,
+        /// Public address of the user that sent the message.
+        pub user_address: Addr,
+    }
+}
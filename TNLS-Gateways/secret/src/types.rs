@@ -17,4 +17,10 @@ pub struct Payload {
     pub user_address: Addr,
     /// User public key from payload encryption (not their wallet public key).
     pub user_key: Binary,
+    /// Optional gas limit for the private contract call, the task fails once it is exceeded.
+    pub gas_limit: Option<u64>,
+    /// Whether the result is encrypted to `user_key`, in the encryption format of the payload,
+    /// before it is sent to the public network.
+    #[serde(default)]
+    pub encrypt_result: bool,
 }
@@ -14,6 +14,7 @@ export interface PreExecutionMsg {
   user_pubkey: Binary;
   handle: string;
   nonce: Binary;
+  payload_version?: number | null;
   [k: string]: unknown;
 }
 export interface Contract {
@@ -1,28 +1,85 @@
-use chacha20poly1305::aead::{Aead, NewAead};
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use hkdf::Hkdf;
 use secp256k1::{ecdh::SharedSecret, PublicKey, SecretKey};
+use sha2::Sha256;
 
 use wasm_bindgen::prelude::*;
 
+/// HKDF info the gateway derives payload keys with.
+const PAYLOAD_KDF_INFO: &[u8] = b"TNLS payload encryption";
+
+/// Encrypts a payload to the gateway in the format of `payload_version`:
+///
+/// | version | AEAD               | KDF         | nonce    | associated data |
+/// |---------|--------------------|-------------|----------|-----------------|
+/// | 1       | ChaCha20-Poly1305  | raw         | 12 bytes | none            |
+/// | 2       | ChaCha20-Poly1305  | HKDF-SHA256 | 12 bytes | none            |
+/// | 3       | XChaCha20-Poly1305 | HKDF-SHA256 | 24 bytes | none            |
+/// | 4       | AES-256-GCM-SIV    | HKDF-SHA256 | 12 bytes | none            |
+/// | 5       | ChaCha20-Poly1305  | HKDF-SHA256 | 12 bytes | task metadata   |
+/// | 6       | XChaCha20-Poly1305 | HKDF-SHA256 | 24 bytes | task metadata   |
+/// | 7       | AES-256-GCM-SIV    | HKDF-SHA256 | 12 bytes | task metadata   |
+///
+/// The same `payload_version` has to be sent with the task. Versions 5 to 7 take the
+/// `associated_data` of [`payload_associated_data`], the others an empty one.
 #[wasm_bindgen]
 pub fn encrypt_payload(
     gateway_public_key: &[u8],
     user_private_key: &[u8],
     plaintext: &[u8],
     nonce: &[u8],
+    payload_version: u8,
+    associated_data: &[u8],
 ) -> Vec<u8> {
     let user_private_key = SecretKey::from_slice(user_private_key).unwrap();
     let gateway_public_key = PublicKey::from_slice(gateway_public_key).unwrap();
     let shared_key = SharedSecret::new(&gateway_public_key, &user_private_key);
 
-    let shared_key = Key::from_slice(shared_key.as_ref()); // 32-bytes
-    let cipher = ChaCha20Poly1305::new(shared_key);
+    // every version after the first derives the key from the shared secret
+    let mut key = [0u8; 32]; // 32-bytes
+    match payload_version {
+        1 => key.copy_from_slice(shared_key.as_ref()),
+        _ => Hkdf::<Sha256>::new(None, shared_key.as_ref())
+            .expand(PAYLOAD_KDF_INFO, &mut key)
+            .unwrap(),
+    }
+
+    // the gateway decrypts the first versions without associated data
+    if payload_version < 5 && !associated_data.is_empty() {
+        panic!("payload version {} has no associated data", payload_version);
+    }
+    let payload = Payload {
+        msg: plaintext,
+        aad: associated_data,
+    };
 
-    let nonce = Nonce::from_slice(nonce); // 12-bytes; unique per message
+    // the nonce has to be unique per message, and 24 bytes long for XChaCha20-Poly1305
+    let ciphertext = match payload_version {
+        1 | 2 | 5 => ChaCha20Poly1305::new(&key.into()).encrypt(nonce.into(), payload),
+        3 | 6 => XChaCha20Poly1305::new(&key.into()).encrypt(nonce.into(), payload),
+        4 | 7 => Aes256GcmSiv::new(&key.into()).encrypt(nonce.into(), payload),
+        _ => panic!("unsupported payload version {}", payload_version),
+    };
 
-    let ciphertext = cipher
-        .encrypt(nonce, plaintext)
-        .expect("encryption failure!"); // NOTE: handle this error to avoid panics!
+    ciphertext.expect("encryption failure!") // NOTE: handle this error to avoid panics!
+}
 
-    return ciphertext;
+/// Returns the task metadata payload versions 5 to 7 are bound to, as the gateway builds it: the
+/// handle, destination code hash, source network and user address, each prefixed with its length
+/// in bytes as a 4 byte big-endian integer. Ethereum user addresses are relayed checksummed.
+#[wasm_bindgen]
+pub fn payload_associated_data(
+    handle: &str,
+    routing_code_hash: &str,
+    source_network: &str,
+    user_address: &str,
+) -> Vec<u8> {
+    let mut associated_data = vec![];
+    for field in [handle, routing_code_hash, source_network, user_address] {
+        associated_data.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        associated_data.extend_from_slice(field.as_bytes());
+    }
+    associated_data
 }
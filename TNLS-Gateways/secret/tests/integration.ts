@@ -5,10 +5,10 @@ import assert from "assert";
 import { PreExecutionMsg, Payload, Binary } from "./GatewayContract";
 import { ecdsaSign, publicKeyConvert } from "secp256k1";
 import { Wallet as EthWallet } from "ethers";
-import { arrayify, hexlify, SigningKey, computeAddress, recoverAddress, recoverPublicKey, keccak256 } from "ethers/lib/utils";
+import { arrayify, hexlify, SigningKey, computeAddress, recoverAddress, recoverPublicKey, keccak256, hashMessage } from "ethers/lib/utils";
 import sha3 from "js-sha3";
-import { createHash, randomBytes } from 'crypto';
-import { encrypt_payload } from './encrypt-payload/pkg'
+import { randomBytes } from 'crypto';
+import { encrypt_payload, payload_associated_data } from './encrypt-payload/pkg'
 import 'dotenv/config'
 
 var mnemonic: string;
@@ -91,6 +91,7 @@ const initializeGateway = async (
         entropy: "secret",
         rng_hash: scrtRngHash,
         rng_addr: scrtRngAddress,
+        permissionless: false,
       },
       codeHash: contractCodeHash,
       label: "My contract" + Math.ceil(Math.random() * 10000), // The label should be unique for every contract, add random string in order to maintain uniqueness
@@ -312,6 +313,9 @@ async function initializeAndUploadContract() {
     scrtRngAddress,
   );
 
+  // the test client relays its own tasks
+  console.log(`Registering relayer...`);
+  await addRelayersTx(client, gatewayHash, gatewayAddress, [client.address]);
   console.log(`Retrieving random number...`);
   await rngTx(client, gatewayHash, gatewayAddress, scrtRngHash, scrtRngAddress);
   console.log(`Sending query: {"get_public_keys": {} }`);
@@ -327,6 +331,10 @@ async function initializeAndUploadContract() {
     gatewayKey,
   );
 
+  // tasks can only be routed to registered private contracts and handles
+  console.log(`Registering example contract route...`);
+  await setRouteTx(client, gatewayHash, gatewayAddress, contractAddress, contractHash, ["add_one"]);
+
   var clientInfo: [SecretNetworkClient, string, string, string, string, string, string] = [
     client,
     gatewayHash,
@@ -373,6 +381,72 @@ async function rngTx(
   console.log(`"key_gen" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
 }
 
+async function addRelayersTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  relayers: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: {
+        add_relayers: { relayers },
+      },
+      sentFunds: [],
+    },
+    {
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  console.log(`"add_relayers" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
+async function setRouteTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  contractAddress: string,
+  contractHash: string,
+  handles: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: {
+        set_route: {
+          routing_info: contractAddress,
+          code_hash: contractHash,
+          handles,
+        },
+      },
+      sentFunds: [],
+    },
+    {
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  console.log(`"set_route" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
 async function gatewayTx(
   client: SecretNetworkClient,
   gatewayHash: string,
@@ -409,12 +483,18 @@ async function gatewayTx(
 
   const plaintext = Buffer
     .from(JSON.stringify(payload));
+  // AES-256-GCM-SIV, with the task metadata bound to the ciphertext
+  const payload_version = 7;
+  const handle = "add_one";
+  const source_network = "ethereum";
   const nonce = arrayify(randomBytes(12));
+  const associated_data = payload_associated_data(handle, routing_code_hash, source_network, user_address);
   let ciphertext = Buffer
-    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce))
+    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce, payload_version, associated_data))
     .toString('base64');
 
-  const payloadHash = createHash('sha256').update(ciphertext,'base64').digest();
+  // Ethereum signed message hash of the Keccak256 hash of the ciphertext, as the gateways recompute it
+  const payloadHash = Buffer.from(arrayify(hashMessage(arrayify(keccak256(Buffer.from(ciphertext, 'base64'))))));
   // const payloadHash64 = payloadHash.toString('base64');
   const payloadSignature = ecdsaSign(payloadHash, userPrivateKeyBytes).signature;
   // const payloadSignature64 = Buffer.from(payloadSignature).toString('base64');
@@ -424,7 +504,7 @@ async function gatewayTx(
 
   const handle_msg: PreExecutionMsg = {
     task_id: 1,
-    handle: "add_one",
+    handle: handle,
     routing_info: routing_info,
     routing_code_hash: routing_code_hash,
     user_address: user_address,
@@ -434,7 +514,8 @@ async function gatewayTx(
     nonce: Buffer.from(nonce).toString('base64'),
     payload_hash: payloadHash.toString('base64'),
     payload_signature: Buffer.from(payloadSignature).toString('base64'),
-    source_network: "ethereum",
+    source_network: source_network,
+    payload_version: payload_version,
   };
   console.log("handle_msg:");
   console.log(handle_msg);
@@ -1,5 +1,6 @@
 import os
 import time
+from base64 import b64decode
 from json import loads
 from logging import WARNING
 from typing import List
@@ -516,3 +517,19 @@ def test_dict_translation():
     translated_dict = translate_dict(dict_to_translate, translation_mechanism)
     assert translated_dict == {"test_translated_1": "test_value_1", "test_key_2": "test_value_2",
                                "test_tuple_1": ["test_value_3", "test_value_4"]}
+
+
+def test_new_task_translation_for_secret():
+    # logNewTask arguments as web3 decodes them, with a versioned payload and a 24 byte nonce
+    task = Task({'task_id': 1, 'source_network': 'ethereum', 'routing_info': 'secret1contract',
+                 'handle': 'add_one', 'nonce': b'n' * 24, 'payload_signature': b's' * 65,
+                 'payload_version': 6})
+    translated = loads(str(task))
+    assert task.task_destination_network == 'secret'
+    assert translated['payload_version'] == 6
+    assert b64decode(translated['nonce']) == b'n' * 24
+    # the recovery byte is cut off the payload signature for the secret gateway
+    assert b64decode(translated['payload_signature']) == b's' * 64
+    with open(f'{os.path.dirname(os.path.dirname(__file__))}/secret_abi.json') as f:
+        secret_args = loads(f.read())['inputs']['args']
+    assert {'nonce', 'payload_version'} <= set(secret_args)
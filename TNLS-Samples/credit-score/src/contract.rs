@@ -70,7 +70,14 @@ fn try_handle(
     let handle = msg.handle.as_str();
     match handle {
         "request_score" => {
-            try_request_score(deps, env, msg.input_values, msg.task_id, msg.input_hash)
+            try_request_score(
+                deps,
+                env,
+                msg.input_values,
+                msg.source_network,
+                msg.task_id,
+                msg.input_hash,
+            )
         }
         _ => Err(StdError::generic_err("invalid handle".to_string())),
     }
@@ -80,6 +87,7 @@ fn try_request_score(
     deps: DepsMut,
     _env: Env,
     input_values: String,
+    source_network: String,
     task_id: u64,
     input_hash: Binary,
 ) -> StdResult<Response> {
@@ -93,6 +101,7 @@ fn try_request_score(
     let callback_msg = GatewayMsg::Output {
         outputs: PostExecutionMsg {
             result,
+            source_network,
             task_id,
             input_hash,
         },
@@ -196,6 +205,7 @@ mod tests {
             input_values: "{\"address\":\"0x249C8753A9CB2a47d97A11D94b2179023B7aBCca\",\"name\":\"bob\",\"offchain_assets\":100,\"onchain_assets\":100,\"liabilities\":100,\"missed_payments\":100,\"income\":100}".to_string(),
             handle: "request_score".to_string(),
             user_address: Addr::unchecked("0x1".to_string()),
+            source_network: "ethereum".to_string(),
             task_id: 1,
             input_hash: to_binary(&"".to_string()).unwrap(),
             signature: to_binary(&"".to_string()).unwrap(),
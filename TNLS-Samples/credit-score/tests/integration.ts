@@ -5,8 +5,8 @@ import assert from "assert";
 import { PreExecutionMsg, Payload, Binary } from "../../../TNLS-Gateways/secret/tests/GatewayContract";
 import { ecdsaSign, publicKeyConvert } from "secp256k1";
 import { Wallet as EthWallet } from "ethers";
-import { arrayify, hexlify, SigningKey, computeAddress, recoverAddress, recoverPublicKey, keccak256 } from "ethers/lib/utils";
-import { createHash, randomBytes } from 'crypto';
+import { arrayify, hexlify, SigningKey, computeAddress, recoverAddress, recoverPublicKey, keccak256, hashMessage } from "ethers/lib/utils";
+import { randomBytes } from 'crypto';
 import { encrypt_payload } from '../../../TNLS-Gateways/secret/tests/encrypt-payload/pkg'
 import 'dotenv/config'
 
@@ -95,6 +95,7 @@ const initializeGateway = async (
         entropy: "secret",
         rng_hash: scrtRngHash,
         rng_addr: scrtRngAddress,
+        permissionless: false,
       },
       code_hash: contractCodeHash.code_hash,
       label: "My contract" + Math.ceil(Math.random() * 10000), // The label should be unique for every contract, add random string in order to maintain uniqueness
@@ -327,6 +328,9 @@ async function initializeAndUploadContract() {
     scrtRngAddress,
   );
 
+  // the test client relays its own tasks
+  console.log(`Registering relayer...`);
+  await addRelayersTx(client, gatewayHash, gatewayAddress, [client.address]);
   console.log(`Retrieving random number...`);
   await rngTx(client, gatewayHash, gatewayAddress, scrtRngHash, scrtRngAddress);
   console.log(`Sending query: {"get_public_keys": {} }`);
@@ -342,6 +346,10 @@ async function initializeAndUploadContract() {
     gatewayKey,
   );
 
+  // tasks can only be routed to registered private contracts and handles
+  console.log(`Registering destination contract route...`);
+  await setRouteTx(client, gatewayHash, gatewayAddress, contractAddress, contractHash, ["request_score"]);
+
   var clientInfo: [SecretNetworkClient, string, string, string, string, string, string] = [
     client,
     gatewayHash,
@@ -388,6 +396,76 @@ async function rngTx(
   console.log(`"key_gen" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
 }
 
+async function addRelayersTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  relayers: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contract_address: gatewayAddress,
+      code_hash: gatewayHash,
+      msg: {
+        add_relayers: { relayers },
+      },
+      sent_funds: [],
+    },
+    {
+      broadcastCheckIntervalMs: BROADCAST_MS,
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  gasTotal += tx.gasUsed;
+  console.log(`"add_relayers" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
+async function setRouteTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  contractAddress: string,
+  contractHash: string,
+  handles: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contract_address: gatewayAddress,
+      code_hash: gatewayHash,
+      msg: {
+        set_route: {
+          routing_info: contractAddress,
+          code_hash: contractHash,
+          handles,
+        },
+      },
+      sent_funds: [],
+    },
+    {
+      broadcastCheckIntervalMs: BROADCAST_MS,
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  gasTotal += tx.gasUsed;
+  console.log(`"set_route" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
 async function gatewayTx(
   client: SecretNetworkClient,
   gatewayHash: string,
@@ -436,10 +514,11 @@ async function gatewayTx(
     .from(JSON.stringify(payload));
   const nonce = arrayify(randomBytes(12));
   let ciphertext = Buffer
-    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce))
+    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce, 1, new Uint8Array()))
     .toString('base64');
 
-  const payloadHash = createHash('sha256').update(ciphertext,'base64').digest();
+  // Ethereum signed message hash of the Keccak256 hash of the ciphertext, as the gateways recompute it
+  const payloadHash = Buffer.from(arrayify(hashMessage(arrayify(keccak256(Buffer.from(ciphertext, 'base64'))))));
   // const payloadHash64 = payloadHash.toString('base64');
   const payloadSignature = ecdsaSign(payloadHash, userPrivateKeyBytes).signature;
   // const payloadSignature64 = Buffer.from(payloadSignature).toString('base64');
@@ -71,7 +71,14 @@ fn try_handle<S: Storage, A: Api, Q: Querier>(
     let handle = msg.handle.as_str();
     match handle {
         "submit_player" => {
-            try_store_input(deps, env, msg.input_values, msg.task_id, msg.input_hash)
+            try_store_input(
+                deps,
+                env,
+                msg.input_values,
+                msg.source_network,
+                msg.task_id,
+                msg.input_hash,
+            )
         }
         // "compare" => try_compare(deps, env, msg.input_values, msg.task_id, msg.input_hash),
         _ => Err(StdError::generic_err("invalid handle".to_string())),
@@ -82,6 +89,7 @@ fn try_store_input<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     _env: Env,
     input_values: String,
+    source_network: String,
     task_id: u64,
     input_hash: Binary,
 ) -> HandleResult {
@@ -117,6 +125,7 @@ fn try_store_input<S: Storage, A: Api, Q: Querier>(
     let callback_msg = GatewayMsg::Output {
         outputs: PostExecutionMsg {
             result,
+            source_network,
             task_id,
             input_hash,
         },
@@ -194,6 +203,7 @@ mod tests {
         let message = PrivContractHandleMsg {
             input_values: "{\"address\":\"0x249C8753A9CB2a47d97A11D94b2179023B7aBCca\",\"name\":\"bob\",\"worth\":2000,\"match_addr\":\"0xb607FE9eF481950D47AEdf71ccB904Ff97806cF7\"}".to_string(),
             handle: "submit_player".to_string(),
+            source_network: "ethereum".to_string(),
             task_id: 1,
             input_hash: to_binary(&"".to_string()).unwrap(),
             signature: to_binary(&"".to_string()).unwrap(),
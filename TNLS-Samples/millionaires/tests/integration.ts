@@ -5,8 +5,8 @@ import assert from "assert";
 import { Payload, Contract, Sender, Binary } from "../../../TNLS-Gateways/secret/tests/GatewayContract";
 import { ecdsaSign } from "secp256k1";
 import { Wallet as EthWallet } from "ethers";
-import { arrayify, SigningKey } from "ethers/lib/utils";
-import { createHash, randomBytes } from 'crypto';
+import { arrayify, SigningKey, hashMessage, keccak256 } from "ethers/lib/utils";
+import { randomBytes } from 'crypto';
 import { encrypt_payload } from '../../../TNLS-Gateways/secret/tests/encrypt-payload/pkg'
 import 'dotenv/config'
 
@@ -95,6 +95,7 @@ const initializeGateway = async (
         entropy: "secret",
         rng_hash: scrtRngHash,
         rng_addr: scrtRngAddress,
+        permissionless: false,
       },
       codeHash: contractCodeHash,
       label: "My contract" + Math.ceil(Math.random() * 10000), // The label should be unique for every contract, add random string in order to maintain uniqueness
@@ -339,6 +340,8 @@ async function initializeAndUploadContract() {
     scrtRngAddress,
   );
 
+  // the test client relays its own tasks
+  await addRelayers(client, gatewayHash, gatewayAddress, [client.address]);
   await generateKeys(client, gatewayHash, gatewayAddress, scrtRngHash, scrtRngAddress);
   const gatewayKey = await queryPubKey(client, gatewayHash, gatewayAddress);
 
@@ -350,6 +353,9 @@ async function initializeAndUploadContract() {
     gatewayKey,
   );
 
+  // tasks can only be routed to registered private contracts and handles
+  await setRoute(client, gatewayHash, gatewayAddress, contractAddress, contractHash, ["submit_player"]);
+
   var clientInfo: [SecretNetworkClient, string, string, string, string, string, string] = [
     client,
     gatewayHash,
@@ -404,6 +410,80 @@ async function generateKeys(
   console.log(`generateKeys used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`); 
 }
 
+async function addRelayers(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  relayers: string[],
+) {
+  const handle_msg = {
+    add_relayers: { relayers },
+  };
+
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: handle_msg,
+      sentFunds: [],
+    },
+    {
+      broadcastCheckIntervalMs: BROADCAST_MS,
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  gasTotal += tx.gasUsed;
+  console.log(`addRelayers used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
+async function setRoute(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  contractAddress: string,
+  contractHash: string,
+  handles: string[],
+) {
+  const handle_msg = {
+    set_route: {
+      routing_info: contractAddress,
+      code_hash: contractHash,
+      handles,
+    },
+  };
+
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: handle_msg,
+      sentFunds: [],
+    },
+    {
+      broadcastCheckIntervalMs: BROADCAST_MS,
+      gasLimit: 5000000,
+    }
+  );
+
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+
+  gasTotal += tx.gasUsed;
+  console.log(`setRoute used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+}
+
 async function gatewayTx(
   client: SecretNetworkClient,
   gatewayHash: string,
@@ -452,10 +532,11 @@ async function gatewayTx(
     .from(JSON.stringify(payload));
   const nonce = arrayify(randomBytes(12));
   let ciphertext = Buffer
-    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce))
+    .from(encrypt_payload(gatewayPublicKeyBytes, userPrivateKeyBytes, plaintext, nonce, 1, new Uint8Array()))
     .toString('base64');
 
-  const payloadHash = createHash('sha256').update(ciphertext,'base64').digest();
+  // Ethereum signed message hash of the Keccak256 hash of the ciphertext, as the gateways recompute it
+  const payloadHash = Buffer.from(arrayify(hashMessage(arrayify(keccak256(Buffer.from(ciphertext, 'base64'))))));
   const payloadHash64 = payloadHash.toString('base64');
   // console.log(`\nPayload Hash is ${payloadHash.byteLength} bytes`);
 
@@ -45,6 +45,9 @@ contract DeployScript is Script {
 
         gatewayAddress.updateRoute(route, verificationAddress, sig);
 
+        // Outbound packets from the secret gateway are signed for the network named "ethereum"
+        gatewayAddress.setNetworkName("ethereum");
+
         vm.stopBroadcast();
     }
 }
@@ -13,6 +13,12 @@ contract Gateway is IGateway {
     /// @notice thrown when the PayloadHash is invalid
     error InvalidPayloadHash();
 
+    /// @notice thrown when the payload version is unsupported or its nonce has the wrong length
+    error InvalidPayloadVersion();
+
+    /// @notice thrown when an outbound message was already received
+    error OutboundAlreadyReceived();
+
     /*//////////////////////////////////////////////////////////////
                               Events
     //////////////////////////////////////////////////////////////*/
@@ -29,11 +35,20 @@ contract Gateway is IGateway {
         bytes user_key,
         bytes user_pubkey,
         string handle,
-        bytes12 nonce
+        bytes nonce,
+        uint8 payload_version
     );
 
     event logCompletedTask(uint256 indexed task_id, bytes32 payload_hash, bytes32 result_hash);
 
+    event logOutbound(
+        uint256 indexed outbound_id,
+        string source_network,
+        string private_contract,
+        bytes result,
+        bytes32 result_hash
+    );
+
     /*//////////////////////////////////////////////////////////////
                              Constructor
     //////////////////////////////////////////////////////////////*/
@@ -99,16 +114,19 @@ contract Gateway is IGateway {
     /// @param _task Task struct
     /// @param _info ExecutionInfo struct
     function preExecution(Util.Task memory _task, Util.ExecutionInfo memory _info) public {
-        bool verifySig;
-
         // Payload hash signature verification
-        verifySig = true;
-        verifySig = Util.recoverSigner(_task.payload_hash, _info.payload_signature) == _task.user_address;
-
-        if (!verifySig) {
+        if (Util.recoverSigner(_task.payload_hash, _info.payload_signature) != _task.user_address) {
             revert InvalidSignature();
         }
 
+        // Payload version verification, the private gateway cannot decrypt anything else
+        {
+            uint256 nonceLength = Util.payloadNonceLength(_info.payload_version);
+            if (nonceLength == 0 || _info.nonce.length != nonceLength) {
+                revert InvalidPayloadVersion();
+            }
+        }
+
         // persisting the task
         tasks[taskId] = _task;
 
@@ -124,7 +142,8 @@ contract Gateway is IGateway {
             _info.user_key,
             _info.user_pubkey,
             _info.handle,
-            _info.nonce
+            _info.nonce,
+            _info.payload_version
             );
 
         taskId++;
@@ -144,6 +163,11 @@ contract Gateway is IGateway {
 
         address checkerAddress = route[_sourceNetwork];
 
+        // Networks without a route have no verification address, and ecrecover returns 0 on failure
+        if (checkerAddress == address(0)) {
+            revert InvalidSignature();
+        }
+
         // Payload hash verification from tasks struct
         bool verifyPayloadHash;
         verifyPayloadHash = _info.payload_hash == tasks[_taskId].payload_hash;
@@ -153,7 +177,7 @@ contract Gateway is IGateway {
 
         // Result signature verification
         verifySig = true;
-        recoveredSigner = Util.modifiedRecoverSigner(_info.result_hash, _info.result_signature, checkerAddress);
+        recoveredSigner = Util.recoverSigner(_info.result_hash, _info.result_signature);
         verifySig = recoveredSigner == checkerAddress;
         if (!verifySig) {
             revert InvalidSignature();
@@ -161,7 +185,7 @@ contract Gateway is IGateway {
 
         // Packet signature verification
         verifySig = true;
-        recoveredSigner = Util.modifiedRecoverSigner(_info.packet_hash, _info.packet_signature, checkerAddress);
+        recoveredSigner = Util.recoverSigner(_info.packet_hash, _info.packet_signature);
         verifySig = recoveredSigner == checkerAddress;
         if (!verifySig) {
             revert InvalidSignature();
@@ -174,4 +198,75 @@ contract Gateway is IGateway {
 
         emit logCompletedTask(_taskId, _info.payload_hash, _info.result_hash);
     }
+
+    /*//////////////////////////////////////////////////////////////
+                                Outbound
+    //////////////////////////////////////////////////////////////*/
+
+    /// @dev name the private gateway uses for this network, part of every outbound packet it signs
+    string public networkName;
+
+    /// @dev Source network ====> Outbound ID ====> received
+    mapping(string => mapping(uint256 => bool)) public outboundReceived;
+
+    /// @notice Set the name the private gateway uses for this network
+    /// @param _networkName Network name
+    function setNetworkName(string memory _networkName) public onlyOwner {
+        networkName = _networkName;
+    }
+
+    /// @notice Receives a message a private contract started on its own
+    /// @dev Outbound IDs are numbered by the private gateway apart from task IDs. The result and packet
+    /// hashes are recomputed from the message, the hashes in `_info` are not trusted
+    /// @param _outboundId Outbound ID of the message
+    /// @param _sourceNetwork Source network of the message
+    /// @param _privateContract Address of the private contract that sent the message
+    /// @param _info PostExecutionInfo struct
+    function receiveOutbound(
+        uint64 _outboundId,
+        string memory _sourceNetwork,
+        string memory _privateContract,
+        Util.PostExecutionInfo memory _info
+    )
+        public
+    {
+        bool verifySig;
+        address recoveredSigner;
+
+        address checkerAddress = route[_sourceNetwork];
+
+        // Networks without a route have no verification address, and ecrecover returns 0 on failure
+        if (checkerAddress == address(0)) {
+            revert InvalidSignature();
+        }
+
+        // Outbound messages are only received once
+        if (outboundReceived[_sourceNetwork][_outboundId]) {
+            revert OutboundAlreadyReceived();
+        }
+
+        // Result signature verification against the result, private contract and outbound ID
+        bytes32 resultHash = Util.getOutboundResultHash(_info.result, _privateContract, _outboundId);
+        verifySig = true;
+        recoveredSigner = Util.recoverSigner(resultHash, _info.result_signature);
+        verifySig = recoveredSigner == checkerAddress;
+        if (!verifySig) {
+            revert InvalidSignature();
+        }
+
+        // Packet signature verification against the whole message sent to this network
+        bytes32 packetHash = Util.getOutboundPacketHash(
+            _sourceNetwork, networkName, _outboundId, _privateContract, _info.result, _info.result_signature
+        );
+        verifySig = true;
+        recoveredSigner = Util.recoverSigner(packetHash, _info.packet_signature);
+        verifySig = recoveredSigner == checkerAddress;
+        if (!verifySig) {
+            revert InvalidSignature();
+        }
+
+        outboundReceived[_sourceNetwork][_outboundId] = true;
+
+        emit logOutbound(_outboundId, _sourceNetwork, _privateContract, _info.result, resultHash);
+    }
 }
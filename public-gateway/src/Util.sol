@@ -17,9 +17,10 @@ library Util {
         bytes user_pubkey;
         string routing_code_hash;
         string handle;
-        bytes12 nonce;
+        bytes nonce;
         bytes payload;
         bytes payload_signature;
+        uint8 payload_version;
     }
 
     struct PostExecutionInfo {
@@ -70,22 +71,71 @@ library Util {
         return ecrecover(_ethSignedMessageHash, v, r, s);
     }
 
-    /// @notice Recover the signer from message hash
-    /// @param _ethSignedMessageHash The message hash from getEthSignedMessageHash()
-    /// @param _signature The signature that needs to be verified
-    /// @param _checkingAddress address for trial/error for v value
-    function modifiedRecoverSigner(bytes32 _ethSignedMessageHash, bytes memory _signature, address _checkingAddress) public pure returns (address) {
-        (bytes32 r, bytes32 s, uint8 v) = splitSignature(_signature);
-        v = 27;
-        if (ecrecover(_ethSignedMessageHash, v, r, s) == _checkingAddress) {
-            return ecrecover(_ethSignedMessageHash, v, r, s);
+    /// @notice Nonce length the private gateway expects for a payload version, 0 if it does not support the version
+    /// @dev Versions 3 and 6 encrypt with XChaCha20-Poly1305, the others with 12 byte nonce AEADs
+    /// @param _payloadVersion Payload version
+    function payloadNonceLength(uint8 _payloadVersion) public pure returns (uint256) {
+        if (_payloadVersion == 0 || _payloadVersion > 7) {
+            return 0;
         }
-        v = 28;
-        if (ecrecover(_ethSignedMessageHash, v, r, s) == _checkingAddress) {
-            return ecrecover(_ethSignedMessageHash, v, r, s);
-        } else {
-            return address(0);
+        if (_payloadVersion == 3 || _payloadVersion == 6) {
+            return 24;
         }
+        return 12;
+    }
+
+    /// @notice EIP-55 checksummed hex string of an address, the form the relayer passes user addresses on in
+    /// @param _address Address
+    function toChecksumString(address _address) public pure returns (string memory) {
+        bytes memory hexChars = "0123456789abcdef";
+        bytes memory lowercase = new bytes(40);
+        for (uint256 i = 0; i < 20; i++) {
+            uint8 b = uint8(uint160(_address) >> (8 * (19 - i)));
+            lowercase[2 * i] = hexChars[b >> 4];
+            lowercase[2 * i + 1] = hexChars[b & 0x0f];
+        }
+
+        // letters are uppercased where the nibble at the same position of the hash is 8 or more
+        bytes32 hash = keccak256(lowercase);
+        bytes memory checksummed = abi.encodePacked("0x", lowercase);
+        for (uint256 i = 0; i < 40; i++) {
+            uint8 nibble = i % 2 == 0 ? uint8(hash[i / 2]) >> 4 : uint8(hash[i / 2]) & 0x0f;
+            if (lowercase[i] >= bytes1("a") && nibble >= 8) {
+                checksummed[i + 2] = bytes1(uint8(lowercase[i]) - 32);
+            }
+        }
+
+        return string(checksummed);
+    }
+
+    /// @notice Task metadata the AEAD tag of payload versions 5 to 7 covers, as the private gateway builds it
+    /// @dev Each field is prefixed with its length in bytes as a 4 byte big-endian integer
+    /// @param _handle Handle called at the destination contract
+    /// @param _routingCodeHash Destination contract code hash
+    /// @param _sourceNetwork Source network of the task
+    /// @param _userAddress User address
+    function getPayloadAssociatedData(
+        string memory _handle,
+        string memory _routingCodeHash,
+        string memory _sourceNetwork,
+        address _userAddress
+    )
+        public
+        pure
+        returns (bytes memory)
+    {
+        return abi.encodePacked(
+            lengthPrefixed(bytes(_handle)),
+            lengthPrefixed(bytes(_routingCodeHash)),
+            lengthPrefixed(bytes(_sourceNetwork)),
+            lengthPrefixed(bytes(toChecksumString(_userAddress)))
+        );
+    }
+
+    /// @notice Prefixes bytes with their length as a 4 byte big-endian integer
+    /// @param _data Bytes to prefix
+    function lengthPrefixed(bytes memory _data) internal pure returns (bytes memory) {
+        return abi.encodePacked(uint32(_data.length), _data);
     }
 
     /// @notice Hashes the encoded message hash
@@ -98,6 +148,59 @@ library Util {
         return keccak256(abi.encodePacked("\x19Ethereum Signed Message:\n32", _messageHash));
     }
 
+    /// @notice Encodes an integer as 8 little endian bytes, like Rust's `u64::to_le_bytes`
+    /// @param _value The integer to encode
+    function toLittleEndian(uint64 _value) public pure returns (bytes8) {
+        uint64 reversed;
+        for (uint256 i = 0; i < 8; i++) {
+            reversed = (reversed << 8) | (_value & 0xff);
+            _value >>= 8;
+        }
+        return bytes8(reversed);
+    }
+
+    /// @notice Get the result hash the private gateway signs for an outbound message
+    /// @dev Mirrors `signed_packet` in the private gateway: the private contract address is the payload,
+    /// and the input hash commits to the result and the outbound ID
+    /// @param _result The result of the private contract
+    /// @param _privateContract Address of the private contract that sent the message
+    /// @param _outboundId Outbound ID of the message
+    function getOutboundResultHash(bytes memory _result, string memory _privateContract, uint64 _outboundId)
+        public
+        pure
+        returns (bytes32)
+    {
+        bytes32 inputHash = sha256(abi.encodePacked(_result, toLittleEndian(_outboundId)));
+        return getEthSignedMessageHash(keccak256(abi.encodePacked(_result, _privateContract, inputHash)));
+    }
+
+    /// @notice Get the packet hash the private gateway signs for an outbound message
+    /// @param _sourceNetwork Network of the private gateway
+    /// @param _destinationNetwork Network of this gateway
+    /// @param _outboundId Outbound ID of the message
+    /// @param _privateContract Address of the private contract that sent the message
+    /// @param _result The result of the private contract
+    /// @param _resultSignature The result signature, whose recovery byte is not part of the packet
+    function getOutboundPacketHash(
+        string memory _sourceNetwork,
+        string memory _destinationNetwork,
+        uint64 _outboundId,
+        string memory _privateContract,
+        bytes memory _result,
+        bytes memory _resultSignature
+    )
+        public
+        pure
+        returns (bytes32)
+    {
+        bytes memory header = abi.encodePacked(
+            _sourceNetwork, _destinationNetwork, toLittleEndian(_outboundId), _privateContract, sha256(bytes(_privateContract))
+        );
+        bytes32 resultHash = getOutboundResultHash(_result, _privateContract, _outboundId);
+        (bytes32 r, bytes32 s,) = splitSignature(_resultSignature);
+        return getEthSignedMessageHash(keccak256(abi.encodePacked(header, _result, resultHash, r, s)));
+    }
+
     /// @notice Get the encoded hash of the inputs for signing
     /// @param _routeInput Route name
     /// @param _verificationAddressInput Address corresponding to the route
@@ -11,4 +11,14 @@ interface IGateway {
     function preExecution(Util.Task memory _task, Util.ExecutionInfo memory _info) external;
 
     function postExecution(uint256 _taskId, string memory _sourceNetwork, Util.PostExecutionInfo memory _info) external;
+
+    function setNetworkName(string memory _networkName) external;
+
+    function receiveOutbound(
+        uint64 _outboundId,
+        string memory _sourceNetwork,
+        string memory _privateContract,
+        Util.PostExecutionInfo memory _info
+    )
+        external;
 }
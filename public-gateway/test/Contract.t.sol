@@ -30,11 +30,20 @@ contract ContractTest is Test {
         bytes user_key,
         bytes user_pubkey,
         string handle,
-        bytes12 nonce
+        bytes nonce,
+        uint8 payload_version
     );
 
     event logCompletedTask(uint256 indexed task_id, bytes32 payload_hash, bytes32 result_hash);
 
+    event logOutbound(
+        uint256 indexed outbound_id,
+        string source_network,
+        string private_contract,
+        bytes result,
+        bytes32 result_hash
+    );
+
     event ComputedResult(uint256 indexed taskId, bytes result);
 
     function setUp() public {
@@ -96,6 +105,20 @@ contract ContractTest is Test {
         return resultSig;
     }
 
+    function getHashSignature(bytes32 _hash, uint256 _foundryPkey) public returns (bytes memory) {
+        (uint8 v4, bytes32 r4, bytes32 s4) = vm.sign(_foundryPkey, _hash);
+        bytes memory hashSig = abi.encodePacked(r4, s4, v4);
+
+        return hashSig;
+    }
+
+    /// @dev Swaps the recovery byte of a signature between 27 and 28, the signature still recovers an address
+    function flipRecoveryId(bytes memory _signature) public pure returns (bytes memory) {
+        _signature[64] = _signature[64] == bytes1(uint8(27)) ? bytes1(uint8(28)) : bytes1(uint8(27));
+
+        return _signature;
+    }
+
     /*//////////////////////////////////////////////////////////////
                            Test Cases
     //////////////////////////////////////////////////////////////*/
@@ -242,7 +265,8 @@ contract ContractTest is Test {
             handle: "some kinda handle",
             nonce: "ssssssssssss",
             payload: payload,
-            payload_signature: getPayloadSignature(payload, 5)
+            payload_signature: getPayloadSignature(payload, 5),
+            payload_version: 1
         });
 
         vm.expectEmit(true, true, true, true);
@@ -258,7 +282,8 @@ contract ContractTest is Test {
             userKey,
             userPublicKey,
             "some kinda handle",
-            "ssssssssssss"
+            "ssssssssssss",
+            1
             );
         gateway.preExecution(assembledTask, assembledInfo);
 
@@ -319,7 +344,8 @@ contract ContractTest is Test {
             handle: "some kinda handle",
             nonce: "ssssssssssss",
             payload: payload,
-            payload_signature: getPayloadSignature(payload, 7)
+            payload_signature: getPayloadSignature(payload, 7),
+            payload_version: 1
         });
 
         vm.expectEmit(true, true, true, true);
@@ -335,13 +361,96 @@ contract ContractTest is Test {
             userKey,
             userPublicKey,
             "some kinda handle",
-            "ssssssssssss"
+            "ssssssssssss",
+            1
             );
         gateway.preExecution(assembledTask, assembledInfo);
 
         vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
     }
 
+    /// @dev Task and execution info of user vm.addr(5) with the given payload version and nonce
+    function getVersionedExecution(uint8 _payloadVersion, bytes memory _nonce)
+        public
+        returns (Util.Task memory, Util.ExecutionInfo memory)
+    {
+        // bytes32 string encoding of "add a bunch of stuff"
+        bytes memory payload = hex"61646420612062756e6368206f66207374756666000000000000000000000000";
+
+        Util.Task memory assembledTask = Util.Task({
+            callback_address: vm.addr(7),
+            callback_selector: bytes4(abi.encodeWithSignature("callback(uint256 _taskId,bytes memory _result)")),
+            user_address: vm.addr(5),
+            source_network: "ethereum",
+            routing_info: "secret",
+            payload_hash: Util.getEthSignedMessageHash(getPayloadHash(payload)),
+            completed: false
+        });
+
+        Util.ExecutionInfo memory assembledInfo = Util.ExecutionInfo({
+            user_key: hex"736f6d65207075626c6963206b65790000000000000000000000000000000000",
+            user_pubkey: hex"040b8d42640a7eded641dd42ad91d7c9ae3644a2412bdff174790012774e5528a30f9f0a630977d53e7a862eb2fb89207fe4fafc824992d281ba0180c6a1fddb4c",
+            routing_code_hash: "some RoutingCodeHash",
+            handle: "some kinda handle",
+            nonce: _nonce,
+            payload: payload,
+            payload_signature: getPayloadSignature(payload, 5),
+            payload_version: _payloadVersion
+        });
+
+        return (assembledTask, assembledInfo);
+    }
+
+    function test_PreExecutionWithEveryPayloadVersion() public {
+        // versions 3 and 6 use XChaCha20-Poly1305 and its 24 byte nonces
+        for (uint8 version = 1; version <= 7; version++) {
+            bytes memory nonce = (version == 3 || version == 6) ? bytes("ssssssssssssssssssssssss") : bytes("ssssssssssss");
+            (Util.Task memory assembledTask, Util.ExecutionInfo memory assembledInfo) = getVersionedExecution(version, nonce);
+
+            // the version and nonce reach the relayer through the event, task IDs start at 1 like versions
+            vm.expectEmit(true, true, true, true);
+            emit logNewTask(
+                version,
+                "ethereum",
+                vm.addr(5),
+                "secret",
+                "some RoutingCodeHash",
+                assembledInfo.payload,
+                assembledTask.payload_hash,
+                assembledInfo.payload_signature,
+                assembledInfo.user_key,
+                assembledInfo.user_pubkey,
+                "some kinda handle",
+                nonce,
+                version
+                );
+            gateway.preExecution(assembledTask, assembledInfo);
+        }
+        assertEq(gateway.taskId(), 8);
+    }
+
+    function test_PreExecutionWithUnsupportedPayloadVersion() public {
+        (Util.Task memory assembledTask, Util.ExecutionInfo memory assembledInfo) = getVersionedExecution(0, "ssssssssssss");
+        vm.expectRevert(abi.encodeWithSignature("InvalidPayloadVersion()"));
+        gateway.preExecution(assembledTask, assembledInfo);
+
+        (assembledTask, assembledInfo) = getVersionedExecution(8, "ssssssssssss");
+        vm.expectRevert(abi.encodeWithSignature("InvalidPayloadVersion()"));
+        gateway.preExecution(assembledTask, assembledInfo);
+    }
+
+    function test_PreExecutionWithWrongNonceLength() public {
+        // a 12 byte nonce for XChaCha20-Poly1305
+        (Util.Task memory assembledTask, Util.ExecutionInfo memory assembledInfo) = getVersionedExecution(3, "ssssssssssss");
+        vm.expectRevert(abi.encodeWithSignature("InvalidPayloadVersion()"));
+        gateway.preExecution(assembledTask, assembledInfo);
+
+        // and a 24 byte nonce for ChaCha20-Poly1305
+        (assembledTask, assembledInfo) = getVersionedExecution(5, "ssssssssssssssssssssssss");
+        vm.expectRevert(abi.encodeWithSignature("InvalidPayloadVersion()"));
+        gateway.preExecution(assembledTask, assembledInfo);
+    }
+
     function test_PostExecution() public {
         test_OwnerCanUpdateRouteWithValidSignature();
         test_PreExecution();
@@ -408,6 +517,243 @@ contract ContractTest is Test {
         vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
     }
 
+    function getPostExecutionInfo() public returns (Util.PostExecutionInfo memory) {
+        // bytes32 string encoding of "add a bunch of stuff"
+        bytes memory payload = hex"61646420612062756e6368206f66207374756666000000000000000000000000";
+        bytes32 payloadHash = Util.getEthSignedMessageHash(getPayloadHash(payload));
+
+        // bytes32 string encoding of "some result"
+        bytes memory result = hex"736f6d6520726573756c74000000000000000000000000000000000000000000";
+        bytes32 resultHash = Util.getEthSignedMessageHash(getResultHash(result));
+
+        return Util.PostExecutionInfo({
+            payload_hash: payloadHash,
+            result: result,
+            result_hash: resultHash,
+            result_signature: getResultSignature(result, 6),
+            packet_hash: resultHash,
+            packet_signature: getResultSignature(result, 6)
+        });
+    }
+
+    function test_PostExecutionWithFlippedResultRecoveryId() public {
+        test_OwnerCanUpdateRouteWithValidSignature();
+        test_PreExecution();
+
+        // the recovery byte is used as signed, not tried both ways
+        Util.PostExecutionInfo memory assembledInfo = getPostExecutionInfo();
+        assembledInfo.result_signature = flipRecoveryId(assembledInfo.result_signature);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.postExecution(1, "secret", assembledInfo);
+    }
+
+    function test_PostExecutionWithFlippedPacketRecoveryId() public {
+        test_OwnerCanUpdateRouteWithValidSignature();
+        test_PreExecution();
+
+        Util.PostExecutionInfo memory assembledInfo = getPostExecutionInfo();
+        assembledInfo.packet_signature = flipRecoveryId(assembledInfo.packet_signature);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.postExecution(1, "secret", assembledInfo);
+    }
+
+    function test_PostExecutionFromNetworkWithoutRoute() public {
+        test_OwnerCanUpdateRouteWithValidSignature();
+        test_PreExecution();
+
+        // networks without a route have no verification address to recover
+        Util.PostExecutionInfo memory assembledInfo = getPostExecutionInfo();
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.postExecution(1, "polygon", assembledInfo);
+    }
+
+    function setUpOutbound() public {
+        test_OwnerCanUpdateRouteWithValidSignature();
+
+        vm.prank(deployer);
+        gateway.setNetworkName("ethereum");
+    }
+
+    /// @dev Signs outbound message 1 of "secret1privatecontract" the way the secret gateway does, with the
+    /// hashes built here instead of with the Util helpers under test
+    function getOutboundInfo() public returns (Util.PostExecutionInfo memory) {
+        bytes memory result = bytes("some result");
+        bytes memory privateContract = bytes("secret1privatecontract");
+
+        // little endian encoding of outbound ID 1
+        bytes8 outboundId = hex"0100000000000000";
+        bytes32 resultHash = Util.getEthSignedMessageHash(
+            keccak256(abi.encodePacked(result, privateContract, sha256(abi.encodePacked(result, outboundId))))
+        );
+        bytes memory resultSignature = getHashSignature(resultHash, 6);
+
+        // the packet commits to the result signature without its recovery byte
+        bytes32 packetHash;
+        {
+            (bytes32 r, bytes32 s,) = Util.splitSignature(resultSignature);
+            bytes memory header =
+                abi.encodePacked("secret", "ethereum", outboundId, privateContract, sha256(privateContract));
+            packetHash = Util.getEthSignedMessageHash(keccak256(abi.encodePacked(header, result, resultHash, r, s)));
+        }
+
+        return Util.PostExecutionInfo({
+            payload_hash: sha256(privateContract),
+            result: result,
+            result_hash: resultHash,
+            result_signature: resultSignature,
+            packet_hash: packetHash,
+            packet_signature: getHashSignature(packetHash, 6)
+        });
+    }
+
+    function test_ToChecksumString() public {
+        // test vectors of EIP-55
+        assertEq(Util.toChecksumString(0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+        assertEq(Util.toChecksumString(0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359), "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359");
+        assertEq(Util.toChecksumString(0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB), "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB");
+        assertEq(Util.toChecksumString(0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb), "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb");
+    }
+
+    function test_GetPayloadAssociatedData() public {
+        // each field is prefixed with its length as a 4 byte big-endian integer, the address is checksummed
+        bytes memory expected =
+            hex"00000011736f6d65206b696e64612068616e646c6500000014736f6d6520526f7574696e67436f64654861736800000008657468657265756d0000002a307835614165623630353346334539344339623941303966333336363934333545374566314265416564";
+        bytes memory associatedData = Util.getPayloadAssociatedData(
+            "some kinda handle", "some RoutingCodeHash", "ethereum", 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed
+        );
+        assertEq(associatedData, expected);
+    }
+
+    function test_ToLittleEndian() public {
+        assertEq(bytes32(Util.toLittleEndian(1)), bytes32(bytes8(hex"0100000000000000")));
+        assertEq(bytes32(Util.toLittleEndian(258)), bytes32(bytes8(hex"0201000000000000")));
+    }
+
+    function test_ReceiveOutbound() public {
+        setUpOutbound();
+
+        string memory sourceNetwork = "secret";
+        string memory privateContract = "secret1privatecontract";
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+
+        // outbound IDs do not need a task created by preExecution
+        vm.expectEmit(true, true, true, true);
+        emit logOutbound(1, sourceNetwork, privateContract, assembledInfo.result, assembledInfo.result_hash);
+
+        gateway.receiveOutbound(1, sourceNetwork, privateContract, assembledInfo);
+
+        assertEq(gateway.outboundReceived(sourceNetwork, 1), true);
+        (,,,,,, bool tempCompleted) = gateway.tasks(1);
+        assertEq(tempCompleted, false);
+    }
+
+    function test_ReceiveOutboundIgnoresSuppliedHashes() public {
+        setUpOutbound();
+
+        // the gateway recomputes the hashes, so the supplied ones carry no weight
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.payload_hash = bytes32(0);
+        assembledInfo.result_hash = bytes32(0);
+        assembledInfo.packet_hash = bytes32(0);
+
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+        assertEq(gateway.outboundReceived("secret", 1), true);
+    }
+
+    function test_ReceiveOutboundTwice() public {
+        test_ReceiveOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        vm.expectRevert(abi.encodeWithSignature("OutboundAlreadyReceived()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithAnotherResult() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.result = bytes("another result");
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithAnotherOutboundId() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(2, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundFromAnotherPrivateContract() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1othercontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundFromAnotherSourceNetwork() public {
+        setUpOutbound();
+
+        // networks without a route have no verification address to recover
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "polygon", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundSentToAnotherNetwork() public {
+        setUpOutbound();
+        vm.prank(deployer);
+        gateway.setNetworkName("polygon");
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithAnotherResultSignature() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.result_signature = getHashSignature(assembledInfo.result_hash, 7);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithAnotherPacketSignature() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.packet_signature = getHashSignature(assembledInfo.packet_hash, 7);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithFlippedResultRecoveryId() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.result_signature = flipRecoveryId(assembledInfo.result_signature);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_ReceiveOutboundWithFlippedPacketRecoveryId() public {
+        setUpOutbound();
+
+        Util.PostExecutionInfo memory assembledInfo = getOutboundInfo();
+        assembledInfo.packet_signature = flipRecoveryId(assembledInfo.packet_signature);
+        vm.expectRevert(abi.encodeWithSignature("InvalidSignature()"));
+        gateway.receiveOutbound(1, "secret", "secret1privatecontract", assembledInfo);
+    }
+
+    function test_NonOwnerCannotSetNetworkName() public {
+        vm.prank(notOwner);
+        vm.expectRevert("UNAUTHORIZED");
+        gateway.setNetworkName("ethereum");
+    }
+
     /*//////////////////////////////////////////////////////////////
                       Stubbed Value Case Setup
     //////////////////////////////////////////////////////////////*/
@@ -448,7 +794,8 @@ contract ContractTest is Test {
             handle: "some kinda handle",
             nonce: "ssssssssssss",
             payload: payload,
-            payload_signature: payloadSignature
+            payload_signature: payloadSignature,
+            payload_version: 1
         });
 
         vm.expectEmit(true, true, true, true);
@@ -464,7 +811,8 @@ contract ContractTest is Test {
             userKey,
             userPublicKey,
             "some kinda handle",
-            "ssssssssssss"
+            "ssssssssssss",
+            1
             );
         gateway.preExecution(assembledTask, assembledInfo);
 
@@ -532,7 +880,7 @@ contract ContractTest is Test {
         // packet
         bytes32 packetHash = hex"923b23c023d0e5e66ac122d9804414f4f9cab06d7a6ce6c4b8c586a1fa57264c";
         bytes memory packetSignature =
-            hex"2db95ebb82b81f8240d952e1c6edf021e098de63d32f1f0d3bbbb7daf0e9edbd3378fc42e31d1041467c76388a35078968f1f6f2eb781b5b83054a1d90ba41ff1b";
+            hex"2db95ebb82b81f8240d952e1c6edf021e098de63d32f1f0d3bbbb7daf0e9edbd3378fc42e31d1041467c76388a35078968f1f6f2eb781b5b83054a1d90ba41ff1c";
 
         Util.PostExecutionInfo memory assembledInfo = Util.PostExecutionInfo({
             payload_hash: payloadHash,
@@ -576,7 +924,8 @@ contract ContractTest is Test {
             handle: "some kinda handle",
             nonce: "ssssssssssss",
             payload: payload,
-            payload_signature: payloadSignature
+            payload_signature: payloadSignature,
+            payload_version: 1
         });
 
         vm.expectEmit(true, true, true, true);
@@ -592,7 +941,8 @@ contract ContractTest is Test {
             userKey,
             userPublicKey,
             "some kinda handle",
-            "ssssssssssss"
+            "ssssssssssss",
+            1
             );
 
         client.send(userAddress, sourceNetwork, routingInfo, payloadHash, assembledInfo);
@@ -637,7 +987,7 @@ contract ContractTest is Test {
         // packet
         bytes32 packetHash = hex"923b23c023d0e5e66ac122d9804414f4f9cab06d7a6ce6c4b8c586a1fa57264c";
         bytes memory packetSignature =
-            hex"2db95ebb82b81f8240d952e1c6edf021e098de63d32f1f0d3bbbb7daf0e9edbd3378fc42e31d1041467c76388a35078968f1f6f2eb781b5b83054a1d90ba41ff1b";
+            hex"2db95ebb82b81f8240d952e1c6edf021e098de63d32f1f0d3bbbb7daf0e9edbd3378fc42e31d1041467c76388a35078968f1f6f2eb781b5b83054a1d90ba41ff1c";
 
         Util.PostExecutionInfo memory assembledInfo = Util.PostExecutionInfo({
             payload_hash: payloadHash,
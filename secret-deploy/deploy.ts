@@ -6,6 +6,8 @@ import 'dotenv/config'
 var mnemonic = process.env.MNEMONIC!;
 var endpoint = process.env.GRPC_WEB_URL!;
 var chainId = process.env.CHAIN_ID!;
+// the relayer that submits tasks to the gateway, the deploying wallet if missing
+var relayerAddress = process.env.RELAYER_ADDRESS;
 
 type PublicKeyResponse = { encryption_key: string, verification_key: string };
 
@@ -75,6 +77,7 @@ const initializeGateway = async (
         entropy: "TNLS rocks",
         rng_hash: scrtRngHash,
         rng_addr: scrtRngAddress,
+        permissionless: false,
       },
       codeHash: contractCodeHash,
       label: "test_secret_gateway" + Math.ceil(Math.random() * 10000), // The label should be unique for every contract, add random string in order to maintain uniqueness
@@ -205,6 +208,8 @@ async function initializeAndUploadContracts() {
     scrtRngHash,
     scrtRngAddress,
   );
+  console.log(`Registering relayer...`);
+  await addRelayersTx(client, gatewayHash, gatewayAddress, [relayerAddress ?? client.address]);
   console.log(`Retrieving random number...`);
   await rngTx(client, gatewayHash, gatewayAddress, scrtRngHash, scrtRngAddress);
   console.log(`Sending query: {"get_public_keys": {} }`);
@@ -217,6 +222,8 @@ async function initializeAndUploadContracts() {
     gatewayAddress,
     gatewayPublicKey,
   );
+  console.log(`Registering sample contract route...`);
+  await setRouteTx(client, gatewayHash, gatewayAddress, contractAddress, contractHash, ["request_score"]);
 
   var clientInfo: [SecretNetworkClient, string, string, string, string, string, string] = [
     client,
@@ -262,6 +269,68 @@ async function rngTx(
   console.log(`"key_gen" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
 };
 
+async function addRelayersTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  relayers: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: {
+        add_relayers: { relayers },
+      },
+      sentFunds: [],
+    },
+    {
+      gasLimit: 5000000,
+    }
+  );
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+  console.log(`"add_relayers" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+};
+
+async function setRouteTx(
+  client: SecretNetworkClient,
+  gatewayHash: string,
+  gatewayAddress: string,
+  contractAddress: string,
+  contractHash: string,
+  handles: string[],
+) {
+  const tx = await client.tx.compute.executeContract(
+    {
+      sender: client.address,
+      contractAddress: gatewayAddress,
+      codeHash: gatewayHash,
+      msg: {
+        set_route: {
+          routing_info: contractAddress,
+          code_hash: contractHash,
+          handles,
+        },
+      },
+      sentFunds: [],
+    },
+    {
+      gasLimit: 5000000,
+    }
+  );
+  if (tx.code !== 0) {
+    throw new Error(
+      `Failed with the following error:\n ${tx.rawLog}`
+    );
+  };
+  console.log(`"set_route" used \x1b[33m${tx.gasUsed}\x1b[0m gas\n`);
+};
+
 async function queryPubKeys(
   client: SecretNetworkClient,
   gatewayHash: string,